@@ -0,0 +1,32 @@
+use chrono::Local;
+use std::io::Write;
+
+/// General log for things WinLux did or would have done, such as dry-run theme switches.
+pub const APP_LOG_FILE: &str = "winlux.log";
+
+/// Appends timestamped `lines` to `file_name` in the log directory. Logging is best
+/// effort; failures are ignored.
+pub fn append(file_name: &str, lines: &[String]) {
+    if lines.is_empty() {
+        return;
+    }
+
+    let Some(log_dir) = crate::paths::log_dir() else {
+        return;
+    };
+    if std::fs::create_dir_all(&log_dir).is_err() {
+        return;
+    }
+    let Ok(mut file) = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_dir.join(file_name))
+    else {
+        return;
+    };
+
+    let timestamp = Local::now().to_rfc3339();
+    for line in lines {
+        let _ = writeln!(file, "[{timestamp}] {line}");
+    }
+}
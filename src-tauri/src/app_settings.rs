@@ -0,0 +1,197 @@
+use crate::models::{
+    AppError, AppResult, AppSettings, AppSettingsPatch, AppSettingsSnapshot, SettingsChange,
+    SettingsChanged,
+};
+use tauri::{AppHandle, Emitter};
+
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+const STARTUP_ENABLED_FIELD: &str = "startup_enabled";
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+#[tauri::command]
+pub fn get_app_settings() -> AppResult<AppSettingsSnapshot> {
+    Ok(AppSettingsSnapshot {
+        solar: crate::commands::get_solar_settings()?,
+        language: crate::i18n::get_language_settings(),
+        startup: crate::commands::get_startup_state()?,
+        geocoder: crate::geocoder::get_geocoder_settings()?,
+        ip_location: crate::location_provider::get_ip_location_settings()?,
+        location_auto_update: crate::location_provider::get_location_auto_update_settings()?,
+        proxy: crate::proxy::get_proxy_settings()?,
+        saved_locations: crate::locations::get_saved_locations()?,
+    })
+}
+
+/// Checks every field in `patch` before saving any of them, then saves them in a single
+/// update. Emits one `SETTINGS_CHANGED_EVENT` with the fields that changed instead of the
+/// per-section events of the individual setters.
+#[tauri::command]
+pub fn update_app_settings(
+    app: AppHandle,
+    patch: AppSettingsPatch,
+) -> AppResult<AppSettingsSnapshot> {
+    let before = get_app_settings()?;
+    let startup_change = patch
+        .startup_enabled
+        .filter(|enabled| *enabled != before.startup.enabled);
+    if startup_change.is_some() && before.startup.portable {
+        return Err(
+            err("errors.startup.portable_unsupported").with_param("field", STARTUP_ENABLED_FIELD)
+        );
+    }
+
+    let previous_locale = crate::i18n::current_shared_locale();
+    let mut previous = None;
+    crate::settings::update(|settings| {
+        previous = Some(settings.clone());
+        apply_patch(settings, &patch)
+    })?;
+
+    if let Some(enabled) = startup_change {
+        if let Err(error) = crate::commands::set_startup_enabled_internal(enabled) {
+            if let Some(previous) = previous {
+                let _ = crate::settings::update(|settings| {
+                    restore_patched_sections(settings, &previous, &patch);
+                    Ok(())
+                });
+            }
+            return Err(error.with_param("field", STARTUP_ENABLED_FIELD));
+        }
+    }
+
+    notify_settings_updated(&app, &patch, previous_locale)?;
+    emit_settings_changed(&app, &before)
+}
+
+/// Emits `SETTINGS_CHANGED_EVENT` with the fields that differ from `before`, if any, and
+/// returns the current snapshot.
+pub(crate) fn emit_settings_changed(
+    app: &AppHandle,
+    before: &AppSettingsSnapshot,
+) -> AppResult<AppSettingsSnapshot> {
+    let after = get_app_settings()?;
+    let changes = diff_snapshots(before, &after);
+    if !changes.is_empty() {
+        let _ = app.emit(
+            SETTINGS_CHANGED_EVENT,
+            &SettingsChanged {
+                changes,
+                settings: after.clone(),
+            },
+        );
+    }
+
+    Ok(after)
+}
+
+/// Runs each section through its module's checks. An error names the section it came from
+/// in its `field` param and discards the whole update.
+fn apply_patch(settings: &mut AppSettings, patch: &AppSettingsPatch) -> AppResult<()> {
+    let in_field = |field: &'static str| move |error: AppError| error.with_param("field", field);
+
+    if let Some(preference) = &patch.language_preference {
+        settings.language_preference = crate::i18n::normalize_language_preference(preference)
+            .map_err(in_field("language_preference"))?;
+    }
+    if let Some(solar) = &patch.solar {
+        crate::commands::apply_solar_patch(&mut settings.solar, solar)
+            .map_err(in_field("solar"))?;
+    }
+    if let Some(geocoder) = &patch.geocoder {
+        crate::geocoder::apply_geocoder_patch(&mut settings.geocoder, geocoder)
+            .map_err(in_field("geocoder"))?;
+    }
+    if let Some(ip_location) = &patch.ip_location {
+        crate::location_provider::apply_ip_location_patch(&mut settings.ip_location, ip_location)
+            .map_err(in_field("ip_location"))?;
+    }
+    if let Some(auto_update) = &patch.location_auto_update {
+        crate::location_provider::apply_location_auto_update_patch(
+            &mut settings.location_auto_update,
+            auto_update,
+        )
+        .map_err(in_field("location_auto_update"))?;
+    }
+    if let Some(proxy) = &patch.proxy {
+        crate::proxy::apply_proxy_patch(&mut settings.proxy, proxy).map_err(in_field("proxy"))?;
+    }
+
+    Ok(())
+}
+
+/// Puts back the sections `patch` touched, leaving anything else saved since then alone.
+fn restore_patched_sections(
+    settings: &mut AppSettings,
+    previous: &AppSettings,
+    patch: &AppSettingsPatch,
+) {
+    if patch.language_preference.is_some() {
+        settings.language_preference = previous.language_preference.clone();
+    }
+    if patch.solar.is_some() {
+        settings.solar = previous.solar.clone();
+    }
+    if patch.geocoder.is_some() {
+        settings.geocoder = previous.geocoder.clone();
+    }
+    if patch.ip_location.is_some() {
+        settings.ip_location = previous.ip_location.clone();
+    }
+    if patch.location_auto_update.is_some() {
+        settings.location_auto_update = previous.location_auto_update.clone();
+    }
+    if patch.proxy.is_some() {
+        settings.proxy = previous.proxy.clone();
+    }
+}
+
+/// Does what the individual setters do after saving, apart from emitting their events.
+fn notify_settings_updated(
+    app: &AppHandle,
+    patch: &AppSettingsPatch,
+    previous_locale: &str,
+) -> AppResult<()> {
+    if patch.language_preference.is_some() {
+        if crate::i18n::current_shared_locale() != previous_locale {
+            crate::locations::spawn_location_relocalization(app.clone());
+        }
+        crate::tray::refresh_tray_language()
+            .map_err(|error| err_with_source("errors.tray.refresh_language_failed", error))?;
+    }
+    if patch.proxy.is_some() {
+        crate::http::reset_shared_client();
+    }
+    if patch.location_auto_update.is_some() {
+        crate::location_provider::notify_location_auto_update_worker();
+    }
+    if patch.solar.is_some() {
+        crate::commands::refresh_auto_theme(app)?;
+    }
+
+    Ok(())
+}
+
+fn diff_snapshots(
+    before: &AppSettingsSnapshot,
+    after: &AppSettingsSnapshot,
+) -> Vec<SettingsChange> {
+    let flatten = |snapshot: &AppSettingsSnapshot| {
+        crate::settings_transfer::flatten_fields(serde_json::to_value(snapshot).unwrap_or_default())
+    };
+
+    crate::settings_transfer::changed_fields(&flatten(before), &flatten(after))
+        .into_iter()
+        .map(|(field, previous, current)| SettingsChange {
+            field,
+            previous,
+            current,
+        })
+        .collect()
+}
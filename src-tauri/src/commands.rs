@@ -1,44 +1,44 @@
+use crate::geocoder::{ReverseRequest, SearchRequest, SEARCH_LIMIT_MAX};
 use crate::models::{
-    AppError, AppResult, GeocodeResult, LanguageSettings, SolarSettings, StartupState,
-    SunTimesResult, ThemeMode, ThemeState,
+    AppError, AppResult, AppSettings, AutoThemeHistoryEntry, GeocodeResult, LanguageSettings,
+    LocationCandidate, SolarSettings, SolarSettingsPatch, StartupState, StoredSolarSettings,
+    SunTimesResult, ThemeDryRunChange, ThemeMode, ThemeState,
 };
+use crate::theme_backend::{RegistryThemeBackend, ThemeBackend};
 use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, Utc};
-use serde::Deserialize;
+use std::collections::VecDeque;
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
 use std::process::Command;
 use std::sync::OnceLock;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::sync::{Mutex, Notify};
-use tokio::time::{sleep, timeout, Instant};
+use tokio::sync::Notify;
+use tokio::time::timeout;
 
 pub const THEME_STATE_CHANGED_EVENT: &str = "theme-state-changed";
 pub const SOLAR_SETTINGS_CHANGED_EVENT: &str = "solar-settings-changed";
 pub const STARTUP_STATE_CHANGED_EVENT: &str = "startup-state-changed";
 pub const AUTO_THEME_CONFIGURATION_REQUIRED_EVENT: &str = "auto-theme-configuration-required";
+pub const AUTO_THEME_HISTORY_APPENDED_EVENT: &str = "auto-theme-history-appended";
+pub const THEME_DRY_RUN_CHANGE_EVENT: &str = "theme-dry-run-change";
 const PERSONALIZE_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
-const SETTINGS_KEY: &str = "Software\\WinLux";
 const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
 const RUN_VALUE_WINLUX: &str = "WinLux";
-const SETTINGS_VALUE_SOLAR_ADDRESS: &str = "SolarAddress";
-const SETTINGS_VALUE_SOLAR_DISPLAY_NAME: &str = "SolarDisplayName";
-const SETTINGS_VALUE_SOLAR_LATITUDE: &str = "SolarLatitude";
-const SETTINGS_VALUE_SOLAR_LONGITUDE: &str = "SolarLongitude";
-const SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED: &str = "SolarAutoThemeEnabled";
-const SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES: &str = "SolarSunsetOffsetMinutes";
 const SOLAR_SUNSET_OFFSET_MINUTES_MAX: i64 = 720;
-const NOMINATIM_SEARCH_URL: &str = "https://nominatim.openstreetmap.org/search";
-const NOMINATIM_USER_AGENT: &str =
-    concat!("WinLux/", env!("CARGO_PKG_VERSION"), " (+https://github.com/Lparksi/WinLux)");
-const NOMINATIM_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const SEARCH_LOCATIONS_DEFAULT_LIMIT: usize = 5;
+const REVERSE_OFFLINE_MAX_DISTANCE_KM: f64 = 50.0;
 const AUTO_THEME_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
 const AUTO_THEME_ERROR_RETRY_INTERVAL: Duration = Duration::from_secs(60);
 const AUTO_THEME_MIN_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+const AUTO_THEME_HISTORY_LIMIT: usize = 100;
 
-static NOMINATIM_RATE_LIMITER: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
 static AUTO_THEME_WORKER_STARTED: OnceLock<()> = OnceLock::new();
 static AUTO_THEME_WAKE_SIGNAL: OnceLock<Notify> = OnceLock::new();
+static AUTO_THEME_HISTORY: OnceLock<std::sync::Mutex<VecDeque<AutoThemeHistoryEntry>>> =
+    OnceLock::new();
+/// Last switch reported in dry run, so it isn't reported again on every wake.
+static DRY_RUN_INTENT: std::sync::Mutex<Option<ThemeState>> = std::sync::Mutex::new(None);
 
 fn err(code: &str) -> AppError {
     AppError::new(code)
@@ -48,13 +48,6 @@ fn err_with_source(code: &str, source: impl ToString) -> AppError {
     AppError::new(code).with_param("source", source.to_string())
 }
 
-#[derive(Debug, Deserialize)]
-struct NominatimItem {
-    lat: String,
-    lon: String,
-    display_name: String,
-}
-
 fn to_tauri_theme(mode: ThemeMode) -> tauri::utils::Theme {
     match mode {
         ThemeMode::Light => tauri::utils::Theme::Light,
@@ -128,7 +121,68 @@ pub fn set_theme_state(
     set_theme_state_for_app(&window.app_handle(), state)
 }
 
+/// Manual switch from the window or the tray. In dry run the switch is logged and emitted
+/// as `THEME_DRY_RUN_CHANGE_EVENT` instead, and the theme is left alone.
 pub fn set_theme_state_for_app(app: &AppHandle, state: ThemeState) -> AppResult<ThemeState> {
+    let mut backend = RegistryThemeBackend::new(app);
+    match switch_theme(&mut backend, state, is_dry_run_enabled())? {
+        ThemeSwitch::Applied(next_state) => Ok(next_state),
+        ThemeSwitch::DryRun(change) => {
+            if change.current != change.intended {
+                report_dry_run_change(app, &change);
+            }
+            Ok(change.current)
+        }
+    }
+}
+
+enum ThemeSwitch {
+    Applied(ThemeState),
+    /// Nothing was written; this is what the switch would have changed.
+    DryRun(ThemeDryRunChange),
+}
+
+fn switch_theme(
+    backend: &mut dyn ThemeBackend,
+    state: ThemeState,
+    dry_run: bool,
+) -> AppResult<ThemeSwitch> {
+    if dry_run {
+        return Ok(ThemeSwitch::DryRun(ThemeDryRunChange {
+            current: backend.current_state()?,
+            intended: state,
+        }));
+    }
+
+    backend.apply_state(state).map(ThemeSwitch::Applied)
+}
+
+/// Writes `state` and updates the main window. Ignores dry run; `RegistryThemeBackend`
+/// goes through here.
+pub(crate) fn apply_theme_state_for_app(
+    app: &AppHandle,
+    state: ThemeState,
+) -> AppResult<ThemeState> {
+    let _ = record_original_theme();
+    write_theme_state(&state)?;
+    let next_state = get_theme_state()?;
+
+    if let Some(window) = app.get_webview_window("main") {
+        apply_window_theme(&window, next_state.apps);
+
+        let _ = window.emit(THEME_STATE_CHANGED_EVENT, &next_state);
+
+        let apps = to_theme_value(next_state.apps);
+        let script = format!("document.documentElement.setAttribute('data-theme', '{apps}');");
+        let _ = window.eval(&script);
+    }
+
+    let _ = app.emit(THEME_STATE_CHANGED_EVENT, &next_state);
+    Ok(next_state)
+}
+
+/// Writes the Personalize values and tells running applications about them.
+pub(crate) fn write_theme_state(state: &ThemeState) -> AppResult<()> {
     use winreg::enums::{HKEY_CURRENT_USER, KEY_SET_VALUE};
     use winreg::RegKey;
 
@@ -150,20 +204,19 @@ pub fn set_theme_state_for_app(app: &AppHandle, state: ThemeState) -> AppResult<
         .map_err(|error| err_with_source("errors.registry.write_system_theme_failed", error))?;
 
     broadcast_theme_changed();
-    let next_state = get_theme_state()?;
+    Ok(())
+}
 
-    if let Some(window) = app.get_webview_window("main") {
-        apply_window_theme(&window, next_state.apps);
+/// Remembers the theme in use before WinLux first changes it, so the uninstaller can put
+/// it back. Installations that predate this record the theme of their next switch.
+fn record_original_theme() -> AppResult<()> {
+    let current = get_theme_state()?;
+    crate::settings::update(|settings| {
+        settings.original_theme.get_or_insert(current);
+        Ok(())
+    })?;
 
-        let _ = window.emit(THEME_STATE_CHANGED_EVENT, &next_state);
-
-        let apps = to_theme_value(next_state.apps);
-        let script = format!("document.documentElement.setAttribute('data-theme', '{apps}');");
-        let _ = window.eval(&script);
-    }
-
-    let _ = app.emit(THEME_STATE_CHANGED_EVENT, &next_state);
-    Ok(next_state)
+    Ok(())
 }
 
 pub fn apply_auto_theme_for_app(app: &AppHandle) -> AppResult<()> {
@@ -178,33 +231,166 @@ fn apply_auto_theme_for_app_and_get_wait_duration(app: &AppHandle) -> AppResult<
     }
 
     let location = settings
-        .location
+        .effective_location()
+        .cloned()
         .ok_or_else(|| err("errors.auto_theme.location_not_saved"))?;
-    let now_local = Local::now();
-    let local_date = now_local.date_naive();
+    let mode = if settings.dry_run_enabled {
+        StepMode::DryRun
+    } else {
+        StepMode::Live
+    };
+    let mut backend = RegistryThemeBackend::new(app);
+    let step = run_auto_theme_step(
+        &mut backend,
+        location,
+        settings.sunset_offset_minutes,
+        Local::now(),
+        mode,
+    )?;
+
+    match step.applied {
+        Some(entry) if mode == StepMode::DryRun => report_dry_run_step(app, entry),
+        Some(entry) => {
+            clear_dry_run_intent();
+            record_auto_theme_history(app, entry);
+        }
+        None => clear_dry_run_intent(),
+    }
+
+    Ok(auto_theme_wait_duration(
+        step.sun_times.seconds_until_next_transition,
+    ))
+}
+
+pub(crate) struct AutoThemeStep {
+    pub sun_times: SunTimesResult,
+    pub applied: Option<AutoThemeHistoryEntry>,
+}
+
+/// Where a step's switch goes, as recorded in its history entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StepMode {
+    Live,
+    /// The switch is computed and reported but the theme is left alone.
+    DryRun,
+    /// Replayed against an in-memory backend by the simulation.
+    Simulated,
+}
+
+pub(crate) fn run_auto_theme_step(
+    backend: &mut dyn ThemeBackend,
+    location: GeocodeResult,
+    sunset_offset_minutes: i64,
+    now_local: DateTime<Local>,
+    mode: StepMode,
+) -> AppResult<AutoThemeStep> {
     let sun_times = build_sun_times_result_with_sunset_offset(
         location,
-        local_date,
+        now_local.date_naive(),
         now_local,
-        settings.sunset_offset_minutes,
+        sunset_offset_minutes,
     )?;
 
     let desired_state = ThemeState {
         apps: sun_times.recommended_theme,
         system: sun_times.recommended_theme,
     };
-    let current_state = get_theme_state()?;
+    let current_state = backend.current_state()?;
+
+    let applied = if current_state != desired_state {
+        if mode != StepMode::DryRun {
+            let _ = backend.apply_state(desired_state)?;
+        }
+        Some(AutoThemeHistoryEntry {
+            at_local: now_local.format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+            at_unix: now_local.timestamp(),
+            previous: current_state.apps,
+            theme: sun_times.recommended_theme,
+            simulated: mode == StepMode::Simulated,
+            dry_run: mode == StepMode::DryRun,
+        })
+    } else {
+        None
+    };
+
+    Ok(AutoThemeStep { sun_times, applied })
+}
 
-    if current_state != desired_state {
-        let _ = set_theme_state_for_app(app, desired_state)?;
+/// Reports a dry-run switch once. The theme never changes in dry run, so every wake
+/// until the next transition finds the same switch pending.
+fn report_dry_run_step(app: &AppHandle, entry: AutoThemeHistoryEntry) {
+    let change = ThemeDryRunChange {
+        current: ThemeState {
+            apps: entry.previous,
+            system: get_theme_state().map_or(entry.previous, |state| state.system),
+        },
+        intended: ThemeState {
+            apps: entry.theme,
+            system: entry.theme,
+        },
+    };
+
+    {
+        let mut last_intent = DRY_RUN_INTENT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if last_intent.as_ref() == Some(&change.intended) {
+            return;
+        }
+        *last_intent = Some(change.intended.clone());
     }
 
-    let seconds_until_next_transition =
-        sun_times.seconds_until_next_transition.max(0) as u64;
-    let wait_duration = Duration::from_secs(seconds_until_next_transition.saturating_add(1))
-        .max(AUTO_THEME_MIN_RECHECK_INTERVAL);
+    report_dry_run_change(app, &change);
+    record_auto_theme_history(app, entry);
+}
 
-    Ok(wait_duration)
+fn report_dry_run_change(app: &AppHandle, change: &ThemeDryRunChange) {
+    crate::app_log::append(
+        crate::app_log::APP_LOG_FILE,
+        &[format!(
+            "dry run: would switch apps {} -> {}, system {} -> {}",
+            change.current.apps.as_str(),
+            change.intended.apps.as_str(),
+            change.current.system.as_str(),
+            change.intended.system.as_str(),
+        )],
+    );
+    let _ = app.emit(THEME_DRY_RUN_CHANGE_EVENT, change);
+}
+
+fn clear_dry_run_intent() {
+    *DRY_RUN_INTENT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+pub(crate) fn auto_theme_wait_duration(seconds_until_next_transition: i64) -> Duration {
+    let seconds_until_next_transition = seconds_until_next_transition.max(0) as u64;
+    Duration::from_secs(seconds_until_next_transition.saturating_add(1))
+        .max(AUTO_THEME_MIN_RECHECK_INTERVAL)
+}
+
+fn auto_theme_history() -> &'static std::sync::Mutex<VecDeque<AutoThemeHistoryEntry>> {
+    AUTO_THEME_HISTORY.get_or_init(|| std::sync::Mutex::new(VecDeque::new()))
+}
+
+pub(crate) fn record_auto_theme_history(app: &AppHandle, entry: AutoThemeHistoryEntry) {
+    if let Ok(mut history) = auto_theme_history().lock() {
+        if history.len() >= AUTO_THEME_HISTORY_LIMIT {
+            history.pop_front();
+        }
+        history.push_back(entry.clone());
+    }
+
+    let _ = app.emit(AUTO_THEME_HISTORY_APPENDED_EVENT, &entry);
+}
+
+#[tauri::command]
+pub fn get_auto_theme_history() -> AppResult<Vec<AutoThemeHistoryEntry>> {
+    let history = auto_theme_history()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(history.iter().cloned().collect())
 }
 
 fn notify_auto_theme_worker() {
@@ -222,8 +408,12 @@ pub fn set_language_preference_for_app(
     app: &AppHandle,
     preference: &str,
 ) -> AppResult<LanguageSettings> {
+    let previous_locale = crate::i18n::current_shared_locale();
     crate::i18n::set_language_preference(preference)?;
     let settings = crate::i18n::get_language_settings();
+    if crate::i18n::current_shared_locale() != previous_locale {
+        crate::locations::spawn_location_relocalization(app.clone());
+    }
 
     let _ = app.emit(crate::i18n::LANGUAGE_CHANGED_EVENT, &settings);
     crate::tray::refresh_tray_language()
@@ -273,6 +463,28 @@ pub async fn geocode_address(address: String) -> AppResult<GeocodeResult> {
     geocode_address_internal(&address).await
 }
 
+/// Returns ranked candidates so the user can pick the intended place before saving.
+/// `country_codes` restricts results to those countries; without it, results in the
+/// system region are listed first.
+#[tauri::command]
+pub async fn search_locations(
+    query: String,
+    limit: Option<usize>,
+    country_codes: Option<Vec<String>>,
+) -> AppResult<Vec<LocationCandidate>> {
+    search_locations_internal(
+        &query,
+        limit.unwrap_or(SEARCH_LOCATIONS_DEFAULT_LIMIT),
+        &country_codes.unwrap_or_default(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn reverse_geocode(latitude: f64, longitude: f64) -> AppResult<LocationCandidate> {
+    reverse_geocode_internal(latitude, longitude).await
+}
+
 #[tauri::command]
 pub fn get_solar_settings() -> AppResult<SolarSettings> {
     get_solar_settings_internal()
@@ -281,15 +493,44 @@ pub fn get_solar_settings() -> AppResult<SolarSettings> {
 #[tauri::command]
 pub async fn save_solar_location(app: AppHandle, address: String) -> AppResult<SolarSettings> {
     let geocode = geocode_address_internal(&address).await?;
-    save_solar_location_internal(&geocode)?;
+    crate::locations::save_solar_location_for_app(&app, &geocode)?;
+
+    finish_solar_location_change(&app)
+}
 
+#[tauri::command]
+pub fn save_solar_coordinates(
+    app: AppHandle,
+    latitude: f64,
+    longitude: f64,
+    label: String,
+) -> AppResult<SolarSettings> {
+    let location = crate::coordinates::manual_location(latitude, longitude, &label)?;
+    crate::locations::save_solar_location_for_app(&app, &location)?;
+
+    finish_solar_location_change(&app)
+}
+
+/// Re-applies the auto theme and reschedules its worker after solar settings were changed
+/// without going through the individual setters.
+pub(crate) fn refresh_auto_theme(app: &AppHandle) -> AppResult<()> {
+    if get_solar_settings_internal()?.auto_theme_enabled {
+        let _ = apply_auto_theme_for_app(app);
+    }
+
+    notify_auto_theme_worker();
+    Ok(())
+}
+
+pub(crate) fn finish_solar_location_change(app: &AppHandle) -> AppResult<SolarSettings> {
     let settings = get_solar_settings_internal()?;
     if settings.auto_theme_enabled {
-        let _ = apply_auto_theme_for_app(&app);
+        let _ = apply_auto_theme_for_app(app);
     }
 
     let _ = app.emit(SOLAR_SETTINGS_CHANGED_EVENT, &settings);
     notify_auto_theme_worker();
+    crate::locations::spawn_location_label_backfill(app.clone());
     Ok(settings)
 }
 
@@ -297,7 +538,7 @@ pub async fn save_solar_location(app: AppHandle, address: String) -> AppResult<S
 pub fn set_auto_theme_enabled(app: AppHandle, enabled: bool) -> AppResult<SolarSettings> {
     if enabled {
         let settings = get_solar_settings_internal()?;
-        if settings.location.is_none() {
+        if settings.effective_location().is_none() {
             return Err(err("errors.auto_theme.location_required_for_enable"));
         }
     }
@@ -330,6 +571,25 @@ pub fn set_sunset_offset_minutes(app: AppHandle, minutes: i64) -> AppResult<Sola
     Ok(settings)
 }
 
+#[tauri::command]
+pub fn set_dry_run_enabled(app: AppHandle, enabled: bool) -> AppResult<SolarSettings> {
+    set_dry_run_enabled_internal(enabled)?;
+
+    let settings = get_solar_settings_internal()?;
+    let _ = app.emit(SOLAR_SETTINGS_CHANGED_EVENT, &settings);
+    notify_auto_theme_worker();
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn set_prefer_offline_geocoding(app: AppHandle, enabled: bool) -> AppResult<SolarSettings> {
+    set_prefer_offline_geocoding_internal(enabled)?;
+
+    let settings = get_solar_settings_internal()?;
+    let _ = app.emit(SOLAR_SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
+
 #[tauri::command]
 pub async fn get_sun_times_by_address(
     address: String,
@@ -341,6 +601,18 @@ pub async fn get_sun_times_by_address(
     build_sun_times_result(geocode, local_date, Local::now())
 }
 
+#[tauri::command]
+pub fn get_sun_times_by_coordinates(
+    latitude: f64,
+    longitude: f64,
+    date: Option<String>,
+) -> AppResult<SunTimesResult> {
+    let location = crate::coordinates::manual_location(latitude, longitude, "")?;
+    let local_date = resolve_target_date(date.as_deref())?;
+
+    build_sun_times_result(location, local_date, Local::now())
+}
+
 #[tauri::command]
 pub fn get_sun_times_by_saved_location(date: Option<String>) -> AppResult<SunTimesResult> {
     let settings = get_solar_settings_internal()?;
@@ -471,111 +743,103 @@ fn resolve_target_date(date: Option<&str>) -> AppResult<NaiveDate> {
 }
 
 fn get_solar_settings_internal() -> AppResult<SolarSettings> {
-    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ};
-    use winreg::RegKey;
-
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let key = match hkcu.open_subkey_with_flags(SETTINGS_KEY, KEY_READ) {
-        Ok(key) => key,
-        Err(_) => {
-            return Ok(SolarSettings {
-                location: None,
-                auto_theme_enabled: false,
-                sunset_offset_minutes: 0,
-            });
-        }
-    };
-
-    let address: String = key.get_value(SETTINGS_VALUE_SOLAR_ADDRESS).unwrap_or_default();
-    let display_name: String = key
-        .get_value(SETTINGS_VALUE_SOLAR_DISPLAY_NAME)
-        .unwrap_or_default();
-    let latitude_raw: String = key
-        .get_value(SETTINGS_VALUE_SOLAR_LATITUDE)
-        .unwrap_or_default();
-    let longitude_raw: String = key
-        .get_value(SETTINGS_VALUE_SOLAR_LONGITUDE)
-        .unwrap_or_default();
-    let auto_theme_enabled_raw: u32 = key
-        .get_value(SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED)
-        .unwrap_or(0);
-    let sunset_offset_minutes_raw: u32 = key
-        .get_value(SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES)
-        .unwrap_or(0);
-    let sunset_offset_minutes =
-        normalize_sunset_offset_minutes(i64::from(sunset_offset_minutes_raw)).unwrap_or(0);
-
-    let location = if address.trim().is_empty() || display_name.trim().is_empty() {
-        None
-    } else {
-        match (
-            latitude_raw.trim().parse::<f64>(),
-            longitude_raw.trim().parse::<f64>(),
-        ) {
-            (Ok(latitude), Ok(longitude)) => Some(GeocodeResult {
-                address,
-                display_name,
-                latitude,
-                longitude,
-            }),
-            _ => None,
-        }
-    };
+    let solar = crate::settings::load()?.solar;
+    let provisional_location = provisional_location_for(solar.location.as_ref(), &solar.time_zone);
 
     Ok(SolarSettings {
-        location,
-        auto_theme_enabled: auto_theme_enabled_raw != 0,
-        sunset_offset_minutes,
+        location: solar.location,
+        provisional_location,
+        auto_theme_enabled: solar.auto_theme_enabled,
+        // Out-of-range stored offsets are already reported and replaced by the store.
+        sunset_offset_minutes: solar.sunset_offset_minutes,
+        dry_run_enabled: solar.dry_run_enabled,
+        prefer_offline_geocoding: solar.prefer_offline_geocoding,
     })
 }
 
-fn save_solar_location_internal(location: &GeocodeResult) -> AppResult<()> {
-    use winreg::enums::HKEY_CURRENT_USER;
-    use winreg::RegKey;
+/// The time zone estimate to schedule by instead of `saved`: when nothing is saved, or when
+/// the system time zone changed since the location was saved and the user hasn't
+/// responded yet.
+fn provisional_location_for(
+    saved: Option<&GeocodeResult>,
+    recorded_time_zone: &str,
+) -> Option<GeocodeResult> {
+    if saved.is_some() && !time_zone_changed_since(recorded_time_zone) {
+        return None;
+    }
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu
-        .create_subkey(SETTINGS_KEY)
-        .map_err(|error| err_with_source("errors.registry.create_settings_failed", error))?;
+    crate::timezone::provisional_location()
+}
 
-    key.set_value(SETTINGS_VALUE_SOLAR_ADDRESS, &location.address.as_str())
-        .map_err(|error| err_with_source("errors.solar.save_address_failed", error))?;
-    key.set_value(
-        SETTINGS_VALUE_SOLAR_DISPLAY_NAME,
-        &location.display_name.as_str(),
-    )
-    .map_err(|error| err_with_source("errors.solar.save_display_name_failed", error))?;
-    key.set_value(
-        SETTINGS_VALUE_SOLAR_LATITUDE,
-        &location.latitude.to_string(),
-    )
-    .map_err(|error| err_with_source("errors.solar.save_latitude_failed", error))?;
-    key.set_value(
-        SETTINGS_VALUE_SOLAR_LONGITUDE,
-        &location.longitude.to_string(),
-    )
-    .map_err(|error| err_with_source("errors.solar.save_longitude_failed", error))?;
+fn time_zone_changed_since(recorded_time_zone: &str) -> bool {
+    match crate::timezone::system_windows_time_zone() {
+        Some(current) if !recorded_time_zone.is_empty() => {
+            !recorded_time_zone.eq_ignore_ascii_case(&current)
+        }
+        _ => false,
+    }
+}
+
+/// Windows time zone recorded when the solar location was last saved or confirmed.
+pub(crate) fn solar_location_time_zone() -> Option<String> {
+    let time_zone = crate::settings::load().ok()?.solar.time_zone;
+    (!time_zone.is_empty()).then_some(time_zone)
+}
+
+/// Marks the solar location as belonging to the current system time zone.
+pub(crate) fn record_solar_location_time_zone() -> AppResult<()> {
+    let time_zone = crate::timezone::system_windows_time_zone().unwrap_or_default();
+    crate::settings::update(|settings| {
+        settings.solar.time_zone = time_zone;
+        Ok(())
+    })?;
 
     Ok(())
 }
 
+/// Sets the solar location and the time zone it was chosen in. Callers save it in the same
+/// `settings::update` as any change that goes with it.
+pub(crate) fn set_solar_location(settings: &mut AppSettings, location: &GeocodeResult) {
+    settings.solar.location = Some(location.clone());
+    settings.solar.time_zone = crate::timezone::system_windows_time_zone().unwrap_or_default();
+}
+
 fn set_auto_theme_enabled_internal(enabled: bool) -> AppResult<()> {
-    use winreg::enums::HKEY_CURRENT_USER;
-    use winreg::RegKey;
+    crate::settings::update(|settings| {
+        settings.solar.auto_theme_enabled = enabled;
+        Ok(())
+    })?;
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu
-        .create_subkey(SETTINGS_KEY)
-        .map_err(|error| err_with_source("errors.registry.create_settings_failed", error))?;
+    Ok(())
+}
+
+fn is_dry_run_enabled() -> bool {
+    crate::settings::load().is_ok_and(|settings| settings.solar.dry_run_enabled)
+}
 
-    let value: u32 = if enabled { 1 } else { 0 };
-    key.set_value(SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED, &value)
-        .map_err(|error| err_with_source("errors.solar.save_auto_theme_enabled_failed", error))?;
+fn set_dry_run_enabled_internal(enabled: bool) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        settings.solar.dry_run_enabled = enabled;
+        Ok(())
+    })?;
 
     Ok(())
 }
 
-fn normalize_sunset_offset_minutes(minutes: i64) -> AppResult<i64> {
+fn is_prefer_offline_geocoding_enabled() -> bool {
+    crate::settings::load().is_ok_and(|settings| settings.solar.prefer_offline_geocoding)
+}
+
+fn set_prefer_offline_geocoding_internal(enabled: bool) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        settings.solar.prefer_offline_geocoding = enabled;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+pub(crate) fn normalize_sunset_offset_minutes(minutes: i64) -> AppResult<i64> {
     if minutes < 0 || minutes > SOLAR_SUNSET_OFFSET_MINUTES_MAX {
         return Err(err("errors.solar.invalid_sunset_offset_minutes")
             .with_param("min", 0)
@@ -586,20 +850,49 @@ fn normalize_sunset_offset_minutes(minutes: i64) -> AppResult<i64> {
     Ok(minutes)
 }
 
-fn set_sunset_offset_minutes_internal(minutes: i64) -> AppResult<()> {
-    use winreg::enums::HKEY_CURRENT_USER;
-    use winreg::RegKey;
+/// Checks solar settings that didn't come through the individual setters, e.g. an import.
+pub(crate) fn validate_solar_settings(solar: &StoredSolarSettings) -> AppResult<()> {
+    if let Some(location) = &solar.location {
+        crate::coordinates::validate_coordinates(location.latitude, location.longitude)?;
+    } else if solar.auto_theme_enabled && crate::timezone::provisional_location().is_none() {
+        // Like `apply_solar_patch`, the provisional location is enough to run auto theme.
+        return Err(err("errors.auto_theme.location_required_for_enable"));
+    }
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu
-        .create_subkey(SETTINGS_KEY)
-        .map_err(|error| err_with_source("errors.registry.create_settings_failed", error))?;
+    normalize_sunset_offset_minutes(solar.sunset_offset_minutes)?;
+    Ok(())
+}
+
+/// Applies an `update_app_settings` patch with the same checks as the individual setters.
+pub(crate) fn apply_solar_patch(
+    solar: &mut StoredSolarSettings,
+    patch: &SolarSettingsPatch,
+) -> AppResult<()> {
+    if let Some(minutes) = patch.sunset_offset_minutes {
+        solar.sunset_offset_minutes = normalize_sunset_offset_minutes(minutes)?;
+    }
+    if let Some(enabled) = patch.auto_theme_enabled {
+        if enabled && solar.location.is_none() && crate::timezone::provisional_location().is_none()
+        {
+            return Err(err("errors.auto_theme.location_required_for_enable"));
+        }
+        solar.auto_theme_enabled = enabled;
+    }
+    if let Some(enabled) = patch.dry_run_enabled {
+        solar.dry_run_enabled = enabled;
+    }
+    if let Some(enabled) = patch.prefer_offline_geocoding {
+        solar.prefer_offline_geocoding = enabled;
+    }
 
-    let value = u32::try_from(minutes)
-        .map_err(|error| err_with_source("errors.solar.save_sunset_offset_minutes_failed", error))?;
+    Ok(())
+}
 
-    key.set_value(SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES, &value)
-        .map_err(|error| err_with_source("errors.solar.save_sunset_offset_minutes_failed", error))?;
+fn set_sunset_offset_minutes_internal(minutes: i64) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        settings.solar.sunset_offset_minutes = minutes;
+        Ok(())
+    })?;
 
     Ok(())
 }
@@ -610,11 +903,21 @@ fn get_startup_state_internal() -> AppResult<StartupState> {
     use winreg::types::FromRegValue;
     use winreg::RegKey;
 
+    if crate::paths::is_portable() {
+        return Ok(StartupState {
+            enabled: false,
+            portable: true,
+        });
+    }
+
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let key = match hkcu.open_subkey_with_flags(RUN_KEY, KEY_READ) {
         Ok(key) => key,
         Err(error) if error.kind() == ErrorKind::NotFound => {
-            return Ok(StartupState { enabled: false });
+            return Ok(StartupState {
+                enabled: false,
+                portable: false,
+            });
         }
         Err(error) => {
             return Err(err_with_source("errors.registry.open_failed", error));
@@ -630,7 +933,10 @@ fn get_startup_state_internal() -> AppResult<StartupState> {
     };
 
     if enabled_by_name {
-        return Ok(StartupState { enabled: true });
+        return Ok(StartupState {
+            enabled: true,
+            portable: false,
+        });
     }
 
     let exe_path = current_exe_text().ok();
@@ -652,15 +958,20 @@ fn get_startup_state_internal() -> AppResult<StartupState> {
 
     Ok(StartupState {
         enabled: enabled_by_command,
+        portable: false,
     })
 }
 
-fn set_startup_enabled_internal(enabled: bool) -> AppResult<()> {
+pub(crate) fn set_startup_enabled_internal(enabled: bool) -> AppResult<()> {
     use std::io::ErrorKind;
     use winreg::enums::HKEY_CURRENT_USER;
     use winreg::types::FromRegValue;
     use winreg::RegKey;
 
+    if crate::paths::is_portable() {
+        return Err(err("errors.startup.portable_unsupported"));
+    }
+
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let (key, _) = hkcu
         .create_subkey(RUN_KEY)
@@ -734,79 +1045,168 @@ fn startup_run_command() -> AppResult<String> {
     Ok(format!("\"{exe_text}\" --startup"))
 }
 
-async fn geocode_address_internal(address: &str) -> AppResult<GeocodeResult> {
+pub(crate) async fn geocode_address_internal(address: &str) -> AppResult<GeocodeResult> {
     let trimmed = address.trim();
     if trimmed.is_empty() {
         return Err(err("errors.address.empty"));
     }
 
-    let client = reqwest::Client::builder()
-        .user_agent(NOMINATIM_USER_AGENT)
-        .build()
-        .map_err(|error| err_with_source("errors.network.client_build_failed", error))?;
+    if let Some((latitude, longitude)) = crate::coordinates::parse_coordinates(trimmed) {
+        return crate::coordinates::manual_location(latitude, longitude, "");
+    }
 
-    wait_for_nominatim_rate_limit().await;
+    let prefer_offline = is_prefer_offline_geocoding_enabled();
+    if prefer_offline {
+        if let Some(location) = crate::offline_geocoder::geocode(trimmed) {
+            return Ok(location);
+        }
+    }
 
-    let response = client
-        .get(NOMINATIM_SEARCH_URL)
-        .query(&[
-            ("q", trimmed),
-            ("format", "jsonv2"),
-            ("limit", "1"),
-            ("addressdetails", "0"),
-        ])
-        .send()
-        .await
-        .map_err(|error| err_with_source("errors.network.openstreetmap_request_failed", error))?;
+    match crate::geocoder::geocode(trimmed).await {
+        Err(error) if !prefer_offline && crate::http::is_network_failure(&error) => {
+            crate::offline_geocoder::geocode(trimmed).ok_or(error)
+        }
+        result => result,
+    }
+}
 
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(err("errors.geocode.http_failed")
-            .with_param("status", status)
-            .with_param("body", body));
+async fn search_locations_internal(
+    query: &str,
+    limit: usize,
+    country_codes: &[String],
+) -> AppResult<Vec<LocationCandidate>> {
+    let trimmed = query.trim();
+    if trimmed.is_empty() {
+        return Err(err("errors.address.empty"));
     }
 
-    let items: Vec<NominatimItem> = response
-        .json()
-        .await
-        .map_err(|error| err_with_source("errors.geocode.parse_failed", error))?;
+    if limit == 0 || limit > SEARCH_LIMIT_MAX {
+        return Err(err("errors.geocoder.invalid_limit")
+            .with_param("min", 1)
+            .with_param("max", SEARCH_LIMIT_MAX)
+            .with_param("value", limit));
+    }
 
-    let first = items
-        .into_iter()
-        .next()
-        .ok_or_else(|| err("errors.geocode.not_found").with_param("address", trimmed))?;
-
-    let latitude = first
-        .lat
-        .parse::<f64>()
-        .map_err(|error| err_with_source("errors.geocode.latitude_parse_failed", error))?;
-    let longitude = first
-        .lon
-        .parse::<f64>()
-        .map_err(|error| err_with_source("errors.geocode.longitude_parse_failed", error))?;
-
-    Ok(GeocodeResult {
-        address: trimmed.to_string(),
-        display_name: first.display_name,
+    let country_codes = normalize_country_codes(country_codes)?;
+
+    if let Some((latitude, longitude)) = crate::coordinates::parse_coordinates(trimmed) {
+        let location = crate::coordinates::manual_location(latitude, longitude, "")?;
+        return Ok(vec![LocationCandidate {
+            display_name: location.display_name,
+            place_type: "coordinates".to_string(),
+            country_code: None,
+            bounding_box: None,
+            latitude: location.latitude,
+            longitude: location.longitude,
+            source: location.source,
+        }]);
+    }
+
+    let prefer_offline = is_prefer_offline_geocoding_enabled();
+    let mut candidates = if prefer_offline {
+        search_offline_candidates(trimmed, limit, &country_codes)
+    } else {
+        Vec::new()
+    };
+
+    if candidates.is_empty() {
+        let geocoder = crate::geocoder::configured_geocoder()?;
+        let request = SearchRequest {
+            query: trimmed,
+            limit,
+            country_codes: &country_codes,
+            language: crate::i18n::current_shared_locale(),
+        };
+
+        candidates = match crate::geocoder::search(geocoder.as_ref(), &request).await {
+            Ok(candidates) => candidates,
+            Err(error) if !prefer_offline && crate::http::is_network_failure(&error) => {
+                let offline_candidates = search_offline_candidates(trimmed, limit, &country_codes);
+                if offline_candidates.is_empty() {
+                    return Err(error);
+                }
+                offline_candidates
+            }
+            Err(error) => return Err(error),
+        };
+    }
+
+    if country_codes.is_empty() {
+        if let Some(region) = crate::i18n::system_region_code() {
+            crate::geocoder::bias_toward_region(&mut candidates, &region);
+        }
+    }
+
+    Ok(candidates)
+}
+
+pub(crate) async fn reverse_geocode_internal(
+    latitude: f64,
+    longitude: f64,
+) -> AppResult<LocationCandidate> {
+    crate::coordinates::validate_coordinates(latitude, longitude)?;
+
+    let offline_candidate = || {
+        crate::offline_geocoder::nearest(latitude, longitude, REVERSE_OFFLINE_MAX_DISTANCE_KM)
+            .map(|city| city.to_candidate())
+    };
+
+    let prefer_offline = is_prefer_offline_geocoding_enabled();
+    if prefer_offline {
+        if let Some(candidate) = offline_candidate() {
+            return Ok(candidate);
+        }
+    }
+
+    let geocoder = crate::geocoder::configured_geocoder()?;
+    let request = ReverseRequest {
         latitude,
         longitude,
-    })
+        language: crate::i18n::current_shared_locale(),
+    };
+
+    match crate::geocoder::reverse(geocoder.as_ref(), &request).await {
+        Ok(Some(candidate)) => Ok(candidate),
+        Ok(None) => offline_candidate().ok_or_else(|| {
+            err("errors.geocode.reverse_not_found")
+                .with_param("latitude", latitude)
+                .with_param("longitude", longitude)
+        }),
+        Err(error) if !prefer_offline && crate::http::is_network_failure(&error) => {
+            offline_candidate().ok_or(error)
+        }
+        Err(error) => Err(error),
+    }
 }
 
-async fn wait_for_nominatim_rate_limit() {
-    let limiter = NOMINATIM_RATE_LIMITER.get_or_init(|| Mutex::new(None));
-    let mut guard = limiter.lock().await;
-    let now = Instant::now();
+fn normalize_country_codes(country_codes: &[String]) -> AppResult<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for code in country_codes {
+        let trimmed = code.trim();
+        if trimmed.len() != 2 || !trimmed.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(err("errors.geocoder.invalid_country_code").with_param("value", trimmed));
+        }
 
-    if let Some(last_request_at) = *guard {
-        let elapsed = now.saturating_duration_since(last_request_at);
-        if elapsed < NOMINATIM_MIN_INTERVAL {
-            sleep(NOMINATIM_MIN_INTERVAL - elapsed).await;
+        let upper = trimmed.to_ascii_uppercase();
+        if !normalized.contains(&upper) {
+            normalized.push(upper);
         }
     }
 
-    *guard = Some(Instant::now());
+    Ok(normalized)
+}
+
+fn search_offline_candidates(
+    query: &str,
+    limit: usize,
+    country_codes: &[String],
+) -> Vec<LocationCandidate> {
+    crate::offline_geocoder::search(query, usize::MAX)
+        .into_iter()
+        .filter(|city| country_codes.is_empty() || country_codes.contains(&city.country_code))
+        .take(limit)
+        .map(|city| city.to_candidate())
+        .collect()
 }
 
 fn broadcast_theme_changed() {
@@ -832,3 +1232,40 @@ fn broadcast_theme_changed() {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_backend::InMemoryThemeBackend;
+
+    fn theme(mode: ThemeMode) -> ThemeState {
+        ThemeState {
+            apps: mode,
+            system: mode,
+        }
+    }
+
+    #[test]
+    fn dry_run_switch_writes_nothing() {
+        let mut backend = InMemoryThemeBackend::new(theme(ThemeMode::Light));
+
+        let switch = switch_theme(&mut backend, theme(ThemeMode::Dark), true).unwrap();
+
+        let ThemeSwitch::DryRun(change) = switch else {
+            panic!("expected a dry-run switch");
+        };
+        assert_eq!(change.current, theme(ThemeMode::Light));
+        assert_eq!(change.intended, theme(ThemeMode::Dark));
+        assert_eq!(backend.state(), &theme(ThemeMode::Light));
+    }
+
+    #[test]
+    fn live_switch_applies_the_state() {
+        let mut backend = InMemoryThemeBackend::new(theme(ThemeMode::Light));
+
+        let switch = switch_theme(&mut backend, theme(ThemeMode::Dark), false).unwrap();
+
+        assert!(matches!(switch, ThemeSwitch::Applied(state) if state == theme(ThemeMode::Dark)));
+        assert_eq!(backend.state(), &theme(ThemeMode::Dark));
+    }
+}
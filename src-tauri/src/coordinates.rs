@@ -0,0 +1,668 @@
+use crate::models::{AppError, AppResult, GeocodeResult, LocationSource};
+
+const LATITUDE_MIN: f64 = -90.0;
+const LATITUDE_MAX: f64 = 90.0;
+const LONGITUDE_MIN: f64 = -180.0;
+const LONGITUDE_MAX: f64 = 180.0;
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+pub fn validate_coordinates(latitude: f64, longitude: f64) -> AppResult<()> {
+    if !latitude.is_finite() || !(LATITUDE_MIN..=LATITUDE_MAX).contains(&latitude) {
+        return Err(err("errors.coordinates.latitude_out_of_range")
+            .with_param("min", LATITUDE_MIN)
+            .with_param("max", LATITUDE_MAX)
+            .with_param("value", latitude));
+    }
+
+    if !longitude.is_finite() || !(LONGITUDE_MIN..=LONGITUDE_MAX).contains(&longitude) {
+        return Err(err("errors.coordinates.longitude_out_of_range")
+            .with_param("min", LONGITUDE_MIN)
+            .with_param("max", LONGITUDE_MAX)
+            .with_param("value", longitude));
+    }
+
+    Ok(())
+}
+
+pub fn format_coordinates(latitude: f64, longitude: f64) -> String {
+    format!("{latitude:.5}, {longitude:.5}")
+}
+
+/// True for manual locations still labelled with their raw coordinates.
+pub fn is_coordinate_only(location: &GeocodeResult) -> bool {
+    location.source == LocationSource::Manual
+        && location.display_name == format_coordinates(location.latitude, location.longitude)
+}
+
+/// Builds a location from user-entered coordinates; an empty label falls back to the
+/// formatted coordinates.
+pub fn manual_location(latitude: f64, longitude: f64, label: &str) -> AppResult<GeocodeResult> {
+    validate_coordinates(latitude, longitude)?;
+
+    let coordinates_text = format_coordinates(latitude, longitude);
+    let trimmed_label = label.trim();
+    let display_name = if trimmed_label.is_empty() {
+        coordinates_text.clone()
+    } else {
+        trimmed_label.to_string()
+    };
+
+    Ok(GeocodeResult {
+        address: coordinates_text,
+        display_name,
+        latitude,
+        longitude,
+        source: LocationSource::Manual,
+    })
+}
+
+const PLUS_CODE_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+const PLUS_CODE_SEPARATOR_POSITION: usize = 8;
+const PLUS_CODE_PAIR_LENGTH: usize = 10;
+const PLUS_CODE_MAX_LENGTH: usize = 15;
+const PLUS_CODE_GRID_COLUMNS: usize = 4;
+const PLUS_CODE_GRID_ROWS: usize = 5;
+const GOOGLE_MAPS_QUERY_KEYS: [&str; 6] = ["q", "query", "ll", "center", "destination", "daddr"];
+
+/// Recognises coordinates pasted in common formats: decimal pairs with optional N/S/E/W,
+/// degrees-minutes-seconds, `geo:` URIs, Google Maps URLs and full Open Location Codes.
+/// Returns `None` when the input does not look like coordinates or is out of range, so it
+/// can be geocoded as an address instead.
+pub fn parse_coordinates(input: &str) -> Option<(f64, f64)> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    parse_geo_uri(trimmed)
+        .or_else(|| parse_google_maps_url(trimmed))
+        .or_else(|| parse_plus_code(trimmed))
+        .or_else(|| parse_coordinate_text(trimmed))
+        .filter(|&(latitude, longitude)| validate_coordinates(latitude, longitude).is_ok())
+}
+
+fn strip_prefix_ignore_ascii_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = input.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&input[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// Android-style `geo:0,0?q=lat,lon(Label)` URIs put the position in `q`, which then
+/// wins over the path.
+fn parse_geo_uri(input: &str) -> Option<(f64, f64)> {
+    let rest = strip_prefix_ignore_ascii_case(input, "geo:")?;
+    let query = reqwest::Url::parse(input).ok().and_then(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "q")
+            .map(|(_, value)| value.into_owned())
+    });
+    if let Some(query) = &query {
+        let position = match query.find('(') {
+            Some(label_start) if query.ends_with(')') => &query[..label_start],
+            _ => query.as_str(),
+        };
+        if let Some(coordinates) = parse_coordinate_text(position) {
+            return Some(coordinates);
+        }
+    }
+
+    let coordinates = rest.split([';', '?']).next()?;
+    let mut values = coordinates.split(',');
+    let latitude = values.next()?.trim().parse::<f64>().ok()?;
+    let longitude = values.next()?.trim().parse::<f64>().ok()?;
+    // `geo:0,0?q=<address>` is a search, not a position at 0,0.
+    if query.is_some() && latitude == 0.0 && longitude == 0.0 {
+        return None;
+    }
+    Some((latitude, longitude))
+}
+
+fn parse_google_maps_url(input: &str) -> Option<(f64, f64)> {
+    let url = reqwest::Url::parse(input).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    let is_google_host = host.starts_with("google.") || host.starts_with("www.google.");
+    let is_google_maps =
+        host.starts_with("maps.google.") || (is_google_host && url.path().starts_with("/maps"));
+    if !is_google_maps {
+        return None;
+    }
+
+    if let Some(coordinates) = parse_google_maps_data_coordinates(url.path()) {
+        return Some(coordinates);
+    }
+
+    if let Some(coordinates) = parse_google_maps_viewport(url.path()) {
+        return Some(coordinates);
+    }
+
+    url.query_pairs()
+        .filter(|(key, _)| GOOGLE_MAPS_QUERY_KEYS.contains(&key.as_ref()))
+        .find_map(|(_, value)| parse_coordinate_text(value.trim_start_matches("loc:")))
+}
+
+/// Place links carry the pin position as `!3d<lat>!4d<lon>`, which is more precise than
+/// the `@lat,lon` viewport centre.
+fn parse_google_maps_data_coordinates(path: &str) -> Option<(f64, f64)> {
+    let latitude_start = path.rfind("!3d")? + 3;
+    let latitude_text = &path[latitude_start..];
+    let latitude_end = latitude_text.find("!4d")?;
+    let latitude = latitude_text[..latitude_end].parse::<f64>().ok()?;
+
+    let longitude_text = &latitude_text[latitude_end + 3..];
+    let longitude_end = longitude_text
+        .find(['!', '/', '?'])
+        .unwrap_or(longitude_text.len());
+    let longitude = longitude_text[..longitude_end].parse::<f64>().ok()?;
+
+    Some((latitude, longitude))
+}
+
+fn parse_google_maps_viewport(path: &str) -> Option<(f64, f64)> {
+    let viewport_start = path.find('@')? + 1;
+    let viewport = path[viewport_start..].split('/').next()?;
+    let mut values = viewport.split(',');
+    let latitude = values.next()?.parse::<f64>().ok()?;
+    let longitude = values.next()?.parse::<f64>().ok()?;
+    Some((latitude, longitude))
+}
+
+fn plus_code_digit(character: char) -> Option<usize> {
+    PLUS_CODE_ALPHABET.find(character)
+}
+
+/// Decodes a full Open Location Code to the centre of its area. Short codes need a
+/// reference locality and are left to the geocoder.
+fn parse_plus_code(input: &str) -> Option<(f64, f64)> {
+    let code = input.to_ascii_uppercase();
+    let separator = code.find('+')?;
+    if separator != PLUS_CODE_SEPARATOR_POSITION || code.matches('+').count() != 1 {
+        return None;
+    }
+
+    let head = &code[..separator];
+    let tail = &code[separator + 1..];
+    let significant_head = match head.find('0') {
+        Some(padding_start) => {
+            let is_valid_padding = padding_start > 0
+                && padding_start % 2 == 0
+                && head[padding_start..]
+                    .chars()
+                    .all(|character| character == '0')
+                && tail.is_empty();
+            if !is_valid_padding {
+                return None;
+            }
+            &head[..padding_start]
+        }
+        None => head,
+    };
+
+    if tail.len() == 1 {
+        return None;
+    }
+
+    let digits = significant_head
+        .chars()
+        .chain(tail.chars())
+        .map(plus_code_digit)
+        .collect::<Option<Vec<usize>>>()?;
+    if digits.len() < 2 || digits.len() > PLUS_CODE_MAX_LENGTH {
+        return None;
+    }
+
+    // The first pair encodes 20-degree bands: 9 latitude rows and 18 longitude columns.
+    if digits[0] >= 9 || digits[1] >= 18 {
+        return None;
+    }
+
+    let pair_digits = digits.len().min(PLUS_CODE_PAIR_LENGTH);
+    let mut latitude = -90.0;
+    let mut longitude = -180.0;
+    let mut place = 400.0;
+    for pair in digits[..pair_digits].chunks(2) {
+        place /= 20.0;
+        latitude += pair[0] as f64 * place;
+        longitude += pair.get(1).copied().unwrap_or(0) as f64 * place;
+    }
+
+    let mut latitude_place = place;
+    let mut longitude_place = place;
+    for digit in &digits[pair_digits..] {
+        latitude_place /= PLUS_CODE_GRID_ROWS as f64;
+        longitude_place /= PLUS_CODE_GRID_COLUMNS as f64;
+        latitude += (digit / PLUS_CODE_GRID_COLUMNS) as f64 * latitude_place;
+        longitude += (digit % PLUS_CODE_GRID_COLUMNS) as f64 * longitude_place;
+    }
+
+    Some((
+        latitude + latitude_place / 2.0,
+        longitude + longitude_place / 2.0,
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoordinateUnit {
+    Degree,
+    Minute,
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CoordinateToken {
+    Number {
+        value: f64,
+        signed: bool,
+        fractional: bool,
+    },
+    Unit(CoordinateUnit),
+    Hemisphere(char),
+    Separator,
+}
+
+#[derive(Debug, Default)]
+struct CoordinatePart {
+    negative: bool,
+    hemisphere: Option<char>,
+    values: Vec<f64>,
+    units: Vec<Option<CoordinateUnit>>,
+    fractional: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoordinateAxis {
+    Latitude,
+    Longitude,
+}
+
+fn normalize_coordinate_text(input: &str) -> String {
+    input
+        .chars()
+        .map(|character| match character {
+            '′' | '’' | '‘' | 'ʹ' | '´' | '`' => '\'',
+            '″' | '”' | '“' | 'ʺ' => '"',
+            'º' | '˚' => '°',
+            '−' | '–' => '-',
+            _ => character,
+        })
+        .collect()
+}
+
+fn tokenize_coordinate_text(input: &str) -> Option<Vec<CoordinateToken>> {
+    let characters: Vec<char> = normalize_coordinate_text(input).chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        match character {
+            _ if character.is_whitespace() => index += 1,
+            ',' | ';' | '/' => {
+                tokens.push(CoordinateToken::Separator);
+                index += 1;
+            }
+            '°' => {
+                tokens.push(CoordinateToken::Unit(CoordinateUnit::Degree));
+                index += 1;
+            }
+            '\'' if characters.get(index + 1) == Some(&'\'') => {
+                tokens.push(CoordinateToken::Unit(CoordinateUnit::Second));
+                index += 2;
+            }
+            '\'' => {
+                tokens.push(CoordinateToken::Unit(CoordinateUnit::Minute));
+                index += 1;
+            }
+            '"' => {
+                tokens.push(CoordinateToken::Unit(CoordinateUnit::Second));
+                index += 1;
+            }
+            '+' | '-' | '.' | '0'..='9' => {
+                let start = index;
+                if character == '+' || character == '-' {
+                    index += 1;
+                }
+                while index < characters.len()
+                    && (characters[index].is_ascii_digit() || characters[index] == '.')
+                {
+                    index += 1;
+                }
+
+                let text: String = characters[start..index].iter().collect();
+                let unsigned_text = text.trim_start_matches(['+', '-']);
+                if !unsigned_text
+                    .chars()
+                    .any(|character| character.is_ascii_digit())
+                {
+                    return None;
+                }
+
+                tokens.push(CoordinateToken::Number {
+                    value: text.parse::<f64>().ok()?,
+                    signed: text.starts_with('-'),
+                    fractional: unsigned_text.contains('.'),
+                });
+            }
+            _ if character.is_alphabetic() => {
+                let start = index;
+                while index < characters.len() && characters[index].is_alphabetic() {
+                    index += 1;
+                }
+
+                let word: String = characters[start..index].iter().collect();
+                let hemisphere = match word.to_uppercase().as_str() {
+                    "N" | "NORTH" => 'N',
+                    "S" | "SOUTH" => 'S',
+                    "E" | "EAST" => 'E',
+                    "W" | "WEST" => 'W',
+                    _ => return None,
+                };
+                tokens.push(CoordinateToken::Hemisphere(hemisphere));
+            }
+            _ => return None,
+        }
+    }
+
+    Some(tokens)
+}
+
+fn group_coordinate_parts(tokens: &[CoordinateToken]) -> Option<Vec<CoordinatePart>> {
+    let mut parts = Vec::new();
+    let mut current = CoordinatePart::default();
+
+    for token in tokens {
+        match *token {
+            CoordinateToken::Separator => {
+                if !current.values.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                } else if current.hemisphere.is_some() {
+                    return None;
+                }
+            }
+            CoordinateToken::Hemisphere(hemisphere) => {
+                if current.values.is_empty() {
+                    if current.hemisphere.is_some() {
+                        return None;
+                    }
+                    current.hemisphere = Some(hemisphere);
+                } else if current.hemisphere.is_some() {
+                    parts.push(std::mem::take(&mut current));
+                    current.hemisphere = Some(hemisphere);
+                } else {
+                    current.hemisphere = Some(hemisphere);
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            CoordinateToken::Number {
+                value,
+                signed,
+                fractional,
+            } => {
+                let last_unit = current.units.last().copied().flatten();
+                let starts_new_part = !current.values.is_empty()
+                    && (signed
+                        || current.values.len() == 3
+                        || last_unit == Some(CoordinateUnit::Second));
+                if starts_new_part {
+                    parts.push(std::mem::take(&mut current));
+                }
+
+                if current.values.is_empty() {
+                    current.negative = signed;
+                } else if signed {
+                    return None;
+                }
+                current.values.push(value.abs());
+                current.units.push(None);
+                current.fractional |= fractional;
+            }
+            CoordinateToken::Unit(unit) => {
+                match current.units.last() {
+                    Some(None) => {}
+                    _ => return None,
+                }
+
+                if unit == CoordinateUnit::Degree && current.values.len() > 1 {
+                    // A second degree mark begins the other coordinate ("31.2° 121.4°").
+                    let value = current.values.pop()?;
+                    current.units.pop();
+                    parts.push(std::mem::take(&mut current));
+                    current.values.push(value);
+                    current.units.push(Some(unit));
+                } else if let Some(last_unit) = current.units.last_mut() {
+                    *last_unit = Some(unit);
+                }
+            }
+        }
+    }
+
+    if !current.values.is_empty() {
+        parts.push(current);
+    } else if current.hemisphere.is_some() {
+        return None;
+    }
+
+    Some(parts)
+}
+
+/// Splits an unmarked run such as `31.23 121.47` or `31 13 48 121 28 12` in half.
+fn split_unmarked_part(part: CoordinatePart) -> Option<Vec<CoordinatePart>> {
+    let is_unmarked = part.hemisphere.is_none() && part.units.iter().all(|unit| unit.is_none());
+    if !is_unmarked || !matches!(part.values.len(), 2 | 4 | 6) {
+        return None;
+    }
+
+    // Two bare integers are far more likely to be part of an address than coordinates.
+    if part.values.len() == 2 && !part.fractional {
+        return None;
+    }
+
+    let half = part.values.len() / 2;
+    let first = CoordinatePart {
+        negative: part.negative,
+        values: part.values[..half].to_vec(),
+        units: vec![None; half],
+        ..CoordinatePart::default()
+    };
+    let second = CoordinatePart {
+        values: part.values[half..].to_vec(),
+        units: vec![None; half],
+        ..CoordinatePart::default()
+    };
+
+    Some(vec![first, second])
+}
+
+fn coordinate_part_degrees(part: &CoordinatePart) -> Option<(Option<CoordinateAxis>, f64)> {
+    let expected_units = [
+        CoordinateUnit::Degree,
+        CoordinateUnit::Minute,
+        CoordinateUnit::Second,
+    ];
+    for (unit, expected_unit) in part.units.iter().zip(expected_units) {
+        if unit.is_some_and(|unit| unit != expected_unit) {
+            return None;
+        }
+    }
+
+    let degrees = *part.values.first()?;
+    let minutes = part.values.get(1).copied().unwrap_or(0.0);
+    let seconds = part.values.get(2).copied().unwrap_or(0.0);
+    if (part.values.len() > 1 && degrees.fract() != 0.0)
+        || (part.values.len() > 2 && minutes.fract() != 0.0)
+        || minutes >= 60.0
+        || seconds >= 60.0
+    {
+        return None;
+    }
+
+    let magnitude = degrees + minutes / 60.0 + seconds / 3600.0;
+    let (axis, hemisphere_negative) = match part.hemisphere {
+        Some('N') => (Some(CoordinateAxis::Latitude), false),
+        Some('S') => (Some(CoordinateAxis::Latitude), true),
+        Some('E') => (Some(CoordinateAxis::Longitude), false),
+        Some('W') => (Some(CoordinateAxis::Longitude), true),
+        _ => (None, false),
+    };
+
+    if part.negative && part.hemisphere.is_some() {
+        return None;
+    }
+
+    let negative = part.negative || hemisphere_negative;
+    Some((axis, if negative { -magnitude } else { magnitude }))
+}
+
+fn parse_coordinate_text(input: &str) -> Option<(f64, f64)> {
+    let tokens = tokenize_coordinate_text(input)?;
+    let mut parts = group_coordinate_parts(&tokens)?;
+    if parts.len() == 1 {
+        parts = split_unmarked_part(parts.pop()?)?;
+    }
+
+    let [first, second] = <[CoordinatePart; 2]>::try_from(parts).ok()?;
+    let (first_axis, first_value) = coordinate_part_degrees(&first)?;
+    let (second_axis, second_value) = coordinate_part_degrees(&second)?;
+
+    use CoordinateAxis::{Latitude, Longitude};
+    match (first_axis, second_axis) {
+        (Some(Latitude) | None, Some(Longitude) | None) => Some((first_value, second_value)),
+        (Some(Longitude), Some(Latitude) | None) | (None, Some(Latitude)) => {
+            Some((second_value, first_value))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_coordinates;
+
+    const TOLERANCE: f64 = 1e-6;
+
+    fn assert_cases(cases: &[(&str, Option<(f64, f64)>)]) {
+        for &(input, expected) in cases {
+            let actual = parse_coordinates(input);
+            match (actual, expected) {
+                (Some((latitude, longitude)), Some((expected_latitude, expected_longitude))) => {
+                    assert!(
+                        (latitude - expected_latitude).abs() < TOLERANCE
+                            && (longitude - expected_longitude).abs() < TOLERANCE,
+                        "{input:?}: expected {expected:?}, got {actual:?}"
+                    );
+                }
+                _ => assert_eq!(actual, expected, "{input:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_decimal_pairs() {
+        assert_cases(&[
+            ("48.8584, 2.2945", Some((48.8584, 2.2945))),
+            ("-33.8688 151.2093", Some((-33.8688, 151.2093))),
+            ("+40.7128;-74.0060", Some((40.7128, -74.006))),
+            ("33.8688 S, 151.2093 E", Some((-33.8688, 151.2093))),
+            ("N 40.7128 W 74.0060", Some((40.7128, -74.006))),
+            ("151.2093E 33.8688S", Some((-33.8688, 151.2093))),
+            ("40.7128 north, 74.0060 west", Some((40.7128, -74.006))),
+        ]);
+    }
+
+    #[test]
+    fn parses_degrees_minutes_seconds() {
+        let paris = Some((
+            48.0 + 51.0 / 60.0 + 29.6 / 3600.0,
+            2.0 + 17.0 / 60.0 + 40.2 / 3600.0,
+        ));
+        assert_cases(&[
+            ("48°51′29.6″N 2°17′40.2″E", paris),
+            ("48°51'29.6\"N 2°17'40.2\"E", paris),
+            ("48°51'29.6''N, 2°17'40.2''E", paris),
+            ("N 48° 51' 29.6\" E 2° 17' 40.2\"", paris),
+            ("33°52′S 151°12′E", Some((-(33.0 + 52.0 / 60.0), 151.2))),
+            ("48 51 29.6 2 17 40.2", paris),
+        ]);
+    }
+
+    #[test]
+    fn parses_geo_uris() {
+        assert_cases(&[
+            ("geo:48.8584,2.2945", Some((48.8584, 2.2945))),
+            ("geo:48.8584,2.2945;u=35", Some((48.8584, 2.2945))),
+            ("GEO:-33.8688,151.2093?z=10", Some((-33.8688, 151.2093))),
+            ("geo:0,0?q=48.85,2.35(Eiffel Tower)", Some((48.85, 2.35))),
+            ("geo:0,0?q=48.85%2C2.35%28Label%29", Some((48.85, 2.35))),
+            ("geo:0,0?q=1600+Amphitheatre+Parkway", None),
+        ]);
+    }
+
+    #[test]
+    fn parses_google_maps_urls() {
+        assert_cases(&[
+            (
+                "https://www.google.com/maps/@48.8584,2.2945,17z",
+                Some((48.8584, 2.2945)),
+            ),
+            (
+                "https://www.google.com/maps/place/Eiffel+Tower/@48.8583701,2.2922926,17z/data=!3m1!4b1!4m6!3m5!1s0x0:0x0!8m2!3d48.8583701!4d2.2944813",
+                Some((48.8583701, 2.2944813)),
+            ),
+            (
+                "https://maps.google.com/?q=48.8584,2.2945",
+                Some((48.8584, 2.2945)),
+            ),
+            (
+                "https://www.google.de/maps?q=loc:-33.8688,151.2093",
+                Some((-33.8688, 151.2093)),
+            ),
+            ("https://www.google.com/maps/place/Eiffel+Tower", None),
+            ("https://example.com/maps/@48.8584,2.2945,17z", None),
+        ]);
+    }
+
+    #[test]
+    fn parses_full_plus_codes_only() {
+        assert_cases(&[
+            ("8FVC9G8F+6W", Some((47.365_562_5, 8.524_812_5))),
+            ("8fvc9g8f+6w", Some((47.365_562_5, 8.524_812_5))),
+            ("8FVC0000+", Some((47.5, 8.5))),
+            ("9G8F+6W", None),
+            ("9G8F+6W Zurich", None),
+            ("8FVC0000+6X", None),
+            ("8FV00000+", None),
+        ]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        assert_cases(&[
+            ("95.0, 10.0", None),
+            ("10.0, 200.0", None),
+            ("-90.5 0.5", None),
+            ("geo:91,0", None),
+            ("geo:0,0?q=91.5,2.35", None),
+            ("https://www.google.com/maps/@48.8584,190.2945,17z", None),
+            ("90.0, -180.0", Some((90.0, -180.0))),
+        ]);
+    }
+
+    #[test]
+    fn leaves_addresses_to_the_geocoder() {
+        assert_cases(&[
+            ("", None),
+            ("   ", None),
+            ("Paris", None),
+            ("221B Baker Street", None),
+            ("1600 Amphitheatre Parkway, Mountain View", None),
+            ("Route 66", None),
+            ("10 20", None),
+            ("North Sydney", None),
+        ]);
+    }
+}
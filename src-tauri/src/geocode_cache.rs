@@ -0,0 +1,170 @@
+use crate::models::{AppError, AppResult, LocationCandidate};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+const CACHE_FILE_NAME: &str = "geocode-cache.json";
+const CACHE_FORMAT_VERSION: u32 = 1;
+const CACHE_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+const CACHE_MAX_ENTRIES: usize = 500;
+
+static CACHE: OnceLock<Mutex<GeocodeCache>> = OnceLock::new();
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GeocodeCache {
+    version: u32,
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    stored_at_unix: i64,
+    candidates: Vec<LocationCandidate>,
+}
+
+impl GeocodeCache {
+    fn remove_expired(&mut self, now_unix: i64) {
+        self.entries
+            .retain(|_, entry| now_unix - entry.stored_at_unix < CACHE_TTL_SECONDS);
+    }
+
+    fn evict_oldest_beyond_limit(&mut self) {
+        if self.entries.len() <= CACHE_MAX_ENTRIES {
+            return;
+        }
+
+        let mut by_age: Vec<(i64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.stored_at_unix, key.clone()))
+            .collect();
+        by_age.sort();
+
+        let excess = self.entries.len() - CACHE_MAX_ENTRIES;
+        for (_, key) in by_age.into_iter().take(excess) {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn search_key(
+    endpoint: &str,
+    language: &str,
+    query: &str,
+    limit: usize,
+    country_codes: &[String],
+) -> String {
+    format!(
+        "search|{endpoint}|{language}|{}|{limit}|{}",
+        normalize_query(query),
+        country_codes.join(",")
+    )
+}
+
+/// Coordinates are rounded to roughly a metre so repeated lookups of the same pin hit.
+pub fn reverse_key(endpoint: &str, language: &str, latitude: f64, longitude: f64) -> String {
+    format!("reverse|{endpoint}|{language}|{latitude:.5},{longitude:.5}")
+}
+
+fn cache_file_path() -> Option<PathBuf> {
+    crate::paths::cache_dir().map(|dir| dir.join(CACHE_FILE_NAME))
+}
+
+fn load_cache() -> GeocodeCache {
+    let Some(path) = cache_file_path() else {
+        return GeocodeCache::default();
+    };
+
+    let mut cache = std::fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<GeocodeCache>(&bytes).ok())
+        .filter(|cache| cache.version == CACHE_FORMAT_VERSION)
+        .unwrap_or_default();
+    cache.version = CACHE_FORMAT_VERSION;
+    cache.remove_expired(Utc::now().timestamp());
+    cache
+}
+
+fn persist_cache(cache: &GeocodeCache) -> std::io::Result<()> {
+    let Some(path) = cache_file_path() else {
+        return Ok(());
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let bytes = serde_json::to_vec(cache)?;
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, bytes)?;
+    std::fs::rename(&temp_path, &path)
+}
+
+fn with_cache<T>(action: impl FnOnce(&mut GeocodeCache) -> T) -> T {
+    let cache = CACHE.get_or_init(|| Mutex::new(load_cache()));
+    let mut guard = match cache.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    action(&mut guard)
+}
+
+/// Returns the stored result for `key` if it has not outlived the TTL.
+pub fn get(key: &str) -> Option<Vec<LocationCandidate>> {
+    let now_unix = Utc::now().timestamp();
+    with_cache(|cache| {
+        cache
+            .entries
+            .get(key)
+            .filter(|entry| now_unix - entry.stored_at_unix < CACHE_TTL_SECONDS)
+            .map(|entry| entry.candidates.clone())
+    })
+}
+
+/// Stores `candidates` and writes the cache through to disk. Write failures only cost a
+/// future cache miss, so they are ignored.
+pub fn insert(key: String, candidates: &[LocationCandidate]) {
+    let now_unix = Utc::now().timestamp();
+    with_cache(|cache| {
+        cache.entries.insert(
+            key,
+            CacheEntry {
+                stored_at_unix: now_unix,
+                candidates: candidates.to_vec(),
+            },
+        );
+        cache.remove_expired(now_unix);
+        cache.evict_oldest_beyond_limit();
+        let _ = persist_cache(cache);
+    });
+}
+
+#[tauri::command]
+pub fn clear_geocode_cache() -> AppResult<()> {
+    with_cache(|cache| {
+        cache.entries.clear();
+
+        let Some(path) = cache_file_path() else {
+            return Ok(());
+        };
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(err_with_source("errors.geocode_cache.clear_failed", error)),
+        }
+    })
+}
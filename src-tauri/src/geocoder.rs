@@ -0,0 +1,914 @@
+use crate::models::{
+    AppError, AppResult, GeocodeBoundingBox, GeocodeResult, GeocoderProvider, GeocoderSettings,
+    GeocoderSettingsPatch, LocationCandidate, LocationSource, StoredGeocoderSettings,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+
+pub const GEOCODER_SETTINGS_CHANGED_EVENT: &str = "geocoder-settings-changed";
+const NOMINATIM_PUBLIC_ENDPOINT: &str = "https://nominatim.openstreetmap.org";
+const PHOTON_PUBLIC_ENDPOINT: &str = "https://photon.komoot.io";
+const PHOTON_PUBLIC_HOST: &str = "photon.komoot.io";
+const NOMINATIM_PUBLIC_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const PHOTON_PUBLIC_MIN_INTERVAL: Duration = Duration::from_millis(500);
+const PHOTON_LANGUAGES: [&str; 4] = ["en", "de", "fr", "it"];
+/// City-level detail, which makes a better label than the nearest house number.
+const NOMINATIM_REVERSE_ZOOM: u8 = 10;
+pub const SEARCH_LIMIT_MAX: usize = 40;
+
+type RateLimiter = Arc<Mutex<Option<Instant>>>;
+
+static RATE_LIMITERS: OnceLock<std::sync::Mutex<HashMap<String, RateLimiter>>> = OnceLock::new();
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+pub struct SearchRequest<'a> {
+    pub query: &'a str,
+    pub limit: usize,
+    /// Upper-case ISO 3166-1 alpha-2 codes; empty means worldwide.
+    pub country_codes: &'a [String],
+    /// BCP 47 tag the result labels should be written in.
+    pub language: &'a str,
+}
+
+pub struct ReverseRequest<'a> {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// BCP 47 tag the result labels should be written in.
+    pub language: &'a str,
+}
+
+/// A geocoding service. Implementations only describe their HTTP contract; the requests
+/// themselves, including rate limiting, are performed by [`search`] and [`reverse`].
+pub trait Geocoder: Send + Sync {
+    fn endpoint(&self) -> &str;
+    /// Minimum spacing between two requests to this endpoint; zero disables throttling.
+    fn min_request_interval(&self) -> Duration;
+    fn search_url(&self) -> String;
+    fn search_query(&self, request: &SearchRequest) -> Vec<(&'static str, String)>;
+    fn parse_search_response(&self, body: &[u8]) -> AppResult<Vec<LocationCandidate>>;
+    fn reverse_url(&self) -> String;
+    fn reverse_query(&self, request: &ReverseRequest) -> Vec<(&'static str, String)>;
+    fn parse_reverse_response(&self, body: &[u8]) -> AppResult<Option<LocationCandidate>>;
+}
+
+pub struct NominatimGeocoder {
+    provider: GeocoderProvider,
+    endpoint: String,
+}
+
+impl NominatimGeocoder {
+    /// The public OSM instance, bound by its usage policy of one request per second.
+    pub fn public(endpoint: String) -> Self {
+        Self {
+            provider: GeocoderProvider::Nominatim,
+            endpoint,
+        }
+    }
+
+    /// A self-hosted instance; no client-side throttling is applied.
+    pub fn self_hosted(endpoint: String) -> Self {
+        Self {
+            provider: GeocoderProvider::NominatimSelfHosted,
+            endpoint,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NominatimItem {
+    lat: String,
+    lon: String,
+    display_name: String,
+    #[serde(default, rename = "type")]
+    item_type: Option<String>,
+    #[serde(default)]
+    addresstype: Option<String>,
+    #[serde(default)]
+    boundingbox: Vec<String>,
+    #[serde(default)]
+    address: Option<NominatimAddress>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NominatimAddress {
+    country_code: Option<String>,
+}
+
+impl NominatimItem {
+    fn into_candidate(self) -> AppResult<LocationCandidate> {
+        let latitude = self
+            .lat
+            .parse::<f64>()
+            .map_err(|error| err_with_source("errors.geocode.latitude_parse_failed", error))?;
+        let longitude = self
+            .lon
+            .parse::<f64>()
+            .map_err(|error| err_with_source("errors.geocode.longitude_parse_failed", error))?;
+
+        Ok(LocationCandidate {
+            display_name: self.display_name,
+            place_type: self.addresstype.or(self.item_type).unwrap_or_default(),
+            country_code: self
+                .address
+                .and_then(|address| address.country_code)
+                .map(|code| code.to_ascii_uppercase()),
+            bounding_box: parse_nominatim_bounding_box(&self.boundingbox),
+            latitude,
+            longitude,
+            source: LocationSource::Geocoded,
+        })
+    }
+}
+
+/// Nominatim orders the box as `[south, north, west, east]`.
+fn parse_nominatim_bounding_box(values: &[String]) -> Option<GeocodeBoundingBox> {
+    let parsed: Vec<f64> = values
+        .iter()
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match parsed.as_slice() {
+        [south, north, west, east] => Some(GeocodeBoundingBox {
+            south: *south,
+            north: *north,
+            west: *west,
+            east: *east,
+        }),
+        _ => None,
+    }
+}
+
+impl Geocoder for NominatimGeocoder {
+    fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn min_request_interval(&self) -> Duration {
+        match self.provider {
+            GeocoderProvider::NominatimSelfHosted => Duration::ZERO,
+            _ => NOMINATIM_PUBLIC_MIN_INTERVAL,
+        }
+    }
+
+    fn search_url(&self) -> String {
+        format!("{}/search", self.endpoint)
+    }
+
+    fn search_query(&self, request: &SearchRequest) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("q", request.query.to_string()),
+            ("format", "jsonv2".to_string()),
+            ("limit", request.limit.to_string()),
+            ("addressdetails", "1".to_string()),
+        ];
+        if !request.country_codes.is_empty() {
+            query.push((
+                "countrycodes",
+                request.country_codes.join(",").to_ascii_lowercase(),
+            ));
+        }
+
+        query
+    }
+
+    fn parse_search_response(&self, body: &[u8]) -> AppResult<Vec<LocationCandidate>> {
+        let items: Vec<NominatimItem> = serde_json::from_slice(body)
+            .map_err(|error| err_with_source("errors.geocode.parse_failed", error))?;
+
+        items
+            .into_iter()
+            .map(NominatimItem::into_candidate)
+            .collect()
+    }
+
+    fn reverse_url(&self) -> String {
+        format!("{}/reverse", self.endpoint)
+    }
+
+    fn reverse_query(&self, request: &ReverseRequest) -> Vec<(&'static str, String)> {
+        vec![
+            ("lat", request.latitude.to_string()),
+            ("lon", request.longitude.to_string()),
+            ("format", "jsonv2".to_string()),
+            ("zoom", NOMINATIM_REVERSE_ZOOM.to_string()),
+            ("addressdetails", "1".to_string()),
+        ]
+    }
+
+    fn parse_reverse_response(&self, body: &[u8]) -> AppResult<Option<LocationCandidate>> {
+        let value: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|error| err_with_source("errors.geocode.parse_failed", error))?;
+        // Points with nothing nearby (open sea) come back as `{"error": "Unable to geocode"}`.
+        if value.get("error").is_some() {
+            return Ok(None);
+        }
+
+        let item: NominatimItem = serde_json::from_value(value)
+            .map_err(|error| err_with_source("errors.geocode.parse_failed", error))?;
+        item.into_candidate().map(Some)
+    }
+}
+
+pub struct PhotonGeocoder {
+    endpoint: String,
+    is_public: bool,
+}
+
+impl PhotonGeocoder {
+    /// Requests to the public komoot instance are throttled; other instances are not.
+    pub fn new(endpoint: String) -> Self {
+        let is_public = reqwest::Url::parse(&endpoint).is_ok_and(|url| {
+            url.host_str()
+                .is_some_and(|host| host.eq_ignore_ascii_case(PHOTON_PUBLIC_HOST))
+        });
+        Self {
+            endpoint,
+            is_public,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotonResponse {
+    #[serde(default)]
+    features: Vec<PhotonFeature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotonFeature {
+    geometry: PhotonGeometry,
+    #[serde(default)]
+    properties: PhotonProperties,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotonGeometry {
+    coordinates: Vec<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PhotonProperties {
+    name: Option<String>,
+    street: Option<String>,
+    housenumber: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    country: Option<String>,
+    countrycode: Option<String>,
+    #[serde(rename = "type")]
+    place_type: Option<String>,
+    osm_value: Option<String>,
+    /// `[min_lon, max_lat, max_lon, min_lat]`
+    extent: Option<Vec<f64>>,
+}
+
+impl PhotonFeature {
+    fn into_candidate(self) -> AppResult<LocationCandidate> {
+        match self.geometry.coordinates.as_slice() {
+            [longitude, latitude, ..] => Ok(LocationCandidate {
+                display_name: self.properties.display_name(),
+                place_type: self
+                    .properties
+                    .place_type
+                    .clone()
+                    .or_else(|| self.properties.osm_value.clone())
+                    .unwrap_or_default(),
+                country_code: self
+                    .properties
+                    .countrycode
+                    .as_deref()
+                    .map(str::to_ascii_uppercase),
+                bounding_box: self.properties.bounding_box(),
+                latitude: *latitude,
+                longitude: *longitude,
+                source: LocationSource::Geocoded,
+            }),
+            _ => Err(err("errors.geocode.parse_failed")
+                .with_param("source", "missing feature coordinates")),
+        }
+    }
+}
+
+impl PhotonProperties {
+    fn display_name(&self) -> String {
+        let street = match (&self.street, &self.housenumber) {
+            (Some(street), Some(number)) => Some(format!("{street} {number}")),
+            (Some(street), None) => Some(street.clone()),
+            _ => None,
+        };
+
+        let mut parts: Vec<String> = Vec::new();
+        for part in [
+            self.name.clone(),
+            street,
+            self.city.clone(),
+            self.state.clone(),
+            self.country.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let part = part.trim().to_string();
+            if !part.is_empty() && !parts.contains(&part) {
+                parts.push(part);
+            }
+        }
+
+        parts.join(", ")
+    }
+
+    fn bounding_box(&self) -> Option<GeocodeBoundingBox> {
+        match self.extent.as_deref()? {
+            [west, north, east, south] => Some(GeocodeBoundingBox {
+                south: *south,
+                north: *north,
+                west: *west,
+                east: *east,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Geocoder for PhotonGeocoder {
+    fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn min_request_interval(&self) -> Duration {
+        if self.is_public {
+            PHOTON_PUBLIC_MIN_INTERVAL
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn search_url(&self) -> String {
+        format!("{}/api", self.endpoint)
+    }
+
+    fn search_query(&self, request: &SearchRequest) -> Vec<(&'static str, String)> {
+        // Photon cannot restrict by country, so over-fetch and let `search` filter.
+        let limit = if request.country_codes.is_empty() {
+            request.limit
+        } else {
+            SEARCH_LIMIT_MAX
+        };
+
+        let mut query = vec![
+            ("q", request.query.to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(language) = photon_language(request.language) {
+            query.push(("lang", language.to_string()));
+        }
+
+        query
+    }
+
+    fn parse_search_response(&self, body: &[u8]) -> AppResult<Vec<LocationCandidate>> {
+        let response: PhotonResponse = serde_json::from_slice(body)
+            .map_err(|error| err_with_source("errors.geocode.parse_failed", error))?;
+
+        response
+            .features
+            .into_iter()
+            .map(PhotonFeature::into_candidate)
+            .collect()
+    }
+
+    fn reverse_url(&self) -> String {
+        format!("{}/reverse", self.endpoint)
+    }
+
+    fn reverse_query(&self, request: &ReverseRequest) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("lat", request.latitude.to_string()),
+            ("lon", request.longitude.to_string()),
+            ("limit", "1".to_string()),
+        ];
+        if let Some(language) = photon_language(request.language) {
+            query.push(("lang", language.to_string()));
+        }
+
+        query
+    }
+
+    fn parse_reverse_response(&self, body: &[u8]) -> AppResult<Option<LocationCandidate>> {
+        Ok(self.parse_search_response(body)?.into_iter().next())
+    }
+}
+
+/// Photon only ships labels for a handful of languages and rejects the rest.
+fn photon_language(language: &str) -> Option<&'static str> {
+    let primary = language.split('-').next().unwrap_or_default();
+    PHOTON_LANGUAGES
+        .iter()
+        .find(|supported| supported.eq_ignore_ascii_case(primary))
+        .copied()
+}
+
+pub fn default_endpoint(provider: GeocoderProvider) -> &'static str {
+    match provider {
+        GeocoderProvider::Nominatim => NOMINATIM_PUBLIC_ENDPOINT,
+        GeocoderProvider::NominatimSelfHosted => "",
+        GeocoderProvider::Photon => PHOTON_PUBLIC_ENDPOINT,
+    }
+}
+
+fn normalize_endpoint(provider: GeocoderProvider, endpoint: &str) -> AppResult<String> {
+    let trimmed = endpoint.trim().trim_end_matches('/');
+    let effective = if trimmed.is_empty() {
+        default_endpoint(provider)
+    } else {
+        trimmed
+    };
+
+    if effective.is_empty() {
+        return Err(err("errors.geocoder.endpoint_required"));
+    }
+
+    let url = reqwest::Url::parse(effective).map_err(|error| {
+        err_with_source("errors.geocoder.invalid_endpoint", error).with_param("endpoint", effective)
+    })?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(err("errors.geocoder.invalid_endpoint")
+            .with_param("endpoint", effective)
+            .with_param("source", url.scheme()));
+    }
+
+    Ok(effective.to_string())
+}
+
+pub fn build_geocoder(settings: &GeocoderSettings) -> AppResult<Box<dyn Geocoder>> {
+    let endpoint = normalize_endpoint(settings.provider, &settings.endpoint)?;
+
+    Ok(match settings.provider {
+        GeocoderProvider::Nominatim => Box::new(NominatimGeocoder::public(endpoint)),
+        GeocoderProvider::NominatimSelfHosted => Box::new(NominatimGeocoder::self_hosted(endpoint)),
+        GeocoderProvider::Photon => Box::new(PhotonGeocoder::new(endpoint)),
+    })
+}
+
+pub fn configured_geocoder() -> AppResult<Box<dyn Geocoder>> {
+    build_geocoder(&read_geocoder_settings())
+}
+
+/// Waits for the endpoint's turn, then requests `url` unless a request for the same
+/// `cache_key` finished while this one waited. `None` means the cache has the answer now.
+async fn fetch(
+    geocoder: &dyn Geocoder,
+    cache_key: &str,
+    url: String,
+    query: &[(&'static str, String)],
+    language: &str,
+) -> AppResult<Option<Vec<u8>>> {
+    wait_for_rate_limit(geocoder).await;
+    if crate::geocode_cache::get(cache_key).is_some() {
+        return Ok(None);
+    }
+
+    let mut is_first_attempt = true;
+    crate::http::get_bytes(&url, query, language, || {
+        let is_retry = !std::mem::replace(&mut is_first_attempt, false);
+        async move {
+            if is_retry {
+                wait_for_rate_limit(geocoder).await;
+            }
+        }
+    })
+    .await
+    .map(Some)
+}
+
+pub async fn search(
+    geocoder: &dyn Geocoder,
+    request: &SearchRequest<'_>,
+) -> AppResult<Vec<LocationCandidate>> {
+    let cache_key = crate::geocode_cache::search_key(
+        geocoder.endpoint(),
+        request.language,
+        request.query,
+        request.limit,
+        request.country_codes,
+    );
+    if let Some(candidates) = crate::geocode_cache::get(&cache_key) {
+        return Ok(candidates);
+    }
+
+    let Some(body) = fetch(
+        geocoder,
+        &cache_key,
+        geocoder.search_url(),
+        &geocoder.search_query(request),
+        request.language,
+    )
+    .await?
+    else {
+        return Ok(crate::geocode_cache::get(&cache_key).unwrap_or_default());
+    };
+
+    let mut candidates = geocoder.parse_search_response(&body)?;
+    if !request.country_codes.is_empty() {
+        candidates.retain(|candidate| {
+            candidate
+                .country_code
+                .as_ref()
+                .is_some_and(|code| request.country_codes.contains(code))
+        });
+    }
+    candidates.truncate(request.limit);
+    crate::geocode_cache::insert(cache_key, &candidates);
+
+    Ok(candidates)
+}
+
+/// Labels the point at `request`; `None` when the provider knows nothing nearby.
+pub async fn reverse(
+    geocoder: &dyn Geocoder,
+    request: &ReverseRequest<'_>,
+) -> AppResult<Option<LocationCandidate>> {
+    let cache_key = crate::geocode_cache::reverse_key(
+        geocoder.endpoint(),
+        request.language,
+        request.latitude,
+        request.longitude,
+    );
+    if let Some(candidates) = crate::geocode_cache::get(&cache_key) {
+        return Ok(candidates.into_iter().next());
+    }
+
+    let Some(body) = fetch(
+        geocoder,
+        &cache_key,
+        geocoder.reverse_url(),
+        &geocoder.reverse_query(request),
+        request.language,
+    )
+    .await?
+    else {
+        let cached = crate::geocode_cache::get(&cache_key).unwrap_or_default();
+        return Ok(cached.into_iter().next());
+    };
+
+    let candidate = geocoder.parse_reverse_response(&body)?;
+    crate::geocode_cache::insert(cache_key, candidate.as_slice());
+
+    Ok(candidate)
+}
+
+/// Moves candidates in `region` ahead of the rest while keeping the provider's ranking
+/// within each group.
+pub fn bias_toward_region(candidates: &mut [LocationCandidate], region: &str) {
+    candidates.sort_by_key(|candidate| {
+        !candidate
+            .country_code
+            .as_deref()
+            .is_some_and(|code| code.eq_ignore_ascii_case(region))
+    });
+}
+
+/// Resolves `query` to its best match with the configured provider.
+pub async fn geocode(query: &str) -> AppResult<GeocodeResult> {
+    let geocoder = configured_geocoder()?;
+    let request = SearchRequest {
+        query,
+        limit: 1,
+        country_codes: &[],
+        language: crate::i18n::current_shared_locale(),
+    };
+    let first = search(geocoder.as_ref(), &request)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| err("errors.geocode.not_found").with_param("address", query))?;
+
+    Ok(GeocodeResult {
+        address: query.to_string(),
+        display_name: first.display_name,
+        latitude: first.latitude,
+        longitude: first.longitude,
+        source: LocationSource::Geocoded,
+    })
+}
+
+async fn wait_for_rate_limit(geocoder: &dyn Geocoder) {
+    let min_interval = geocoder.min_request_interval();
+    if min_interval.is_zero() {
+        return;
+    }
+
+    let limiter = {
+        let limiters = RATE_LIMITERS.get_or_init(|| std::sync::Mutex::new(HashMap::new()));
+        let mut guard = match limiters.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        guard
+            .entry(geocoder.endpoint().to_string())
+            .or_default()
+            .clone()
+    };
+
+    let mut guard = limiter.lock().await;
+    let now = Instant::now();
+
+    if let Some(last_request_at) = *guard {
+        let elapsed = now.saturating_duration_since(last_request_at);
+        if elapsed < min_interval {
+            sleep(min_interval - elapsed).await;
+        }
+    }
+
+    *guard = Some(Instant::now());
+}
+
+#[tauri::command]
+pub fn get_geocoder_settings() -> AppResult<GeocoderSettings> {
+    Ok(read_geocoder_settings())
+}
+
+#[tauri::command]
+pub fn set_geocoder_settings(
+    app: AppHandle,
+    provider: GeocoderProvider,
+    endpoint: String,
+) -> AppResult<GeocoderSettings> {
+    let endpoint = endpoint.trim().trim_end_matches('/').to_string();
+    let settings = GeocoderSettings {
+        provider,
+        default_endpoint: default_endpoint(provider).to_string(),
+        endpoint,
+    };
+    build_geocoder(&settings)?;
+    write_geocoder_settings(&settings)?;
+
+    let settings = read_geocoder_settings();
+    let _ = app.emit(GEOCODER_SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
+
+pub(crate) fn validate_geocoder_settings(stored: &StoredGeocoderSettings) -> AppResult<()> {
+    normalize_endpoint(stored.provider, &stored.endpoint)?;
+    Ok(())
+}
+
+pub(crate) fn apply_geocoder_patch(
+    stored: &mut StoredGeocoderSettings,
+    patch: &GeocoderSettingsPatch,
+) -> AppResult<()> {
+    if let Some(provider) = patch.provider {
+        stored.provider = provider;
+    }
+    if let Some(endpoint) = &patch.endpoint {
+        stored.endpoint = endpoint.trim().trim_end_matches('/').to_string();
+    }
+
+    validate_geocoder_settings(stored)
+}
+
+fn read_geocoder_settings() -> GeocoderSettings {
+    let stored = crate::settings::load()
+        .map(|settings| settings.geocoder)
+        .unwrap_or_default();
+
+    GeocoderSettings {
+        provider: stored.provider,
+        endpoint: stored.endpoint,
+        default_endpoint: default_endpoint(stored.provider).to_string(),
+    }
+}
+
+fn write_geocoder_settings(settings: &GeocoderSettings) -> AppResult<()> {
+    crate::settings::update(|stored| {
+        stored.geocoder = StoredGeocoderSettings {
+            provider: settings.provider,
+            endpoint: settings.endpoint.clone(),
+        };
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOMINATIM_SEARCH: &str = r#"[
+        {
+            "lat": "52.5170365",
+            "lon": "13.3888599",
+            "display_name": "Berlin, Deutschland",
+            "type": "administrative",
+            "addresstype": "city",
+            "boundingbox": ["52.3382448", "52.6755087", "13.0883450", "13.7611609"],
+            "address": { "city": "Berlin", "country_code": "de" }
+        },
+        {
+            "lat": "44.4733",
+            "lon": "-71.1859",
+            "display_name": "Berlin, Coos County, New Hampshire, United States",
+            "type": "town",
+            "boundingbox": ["44.4", "44.5", "not a number", "-71.1"]
+        }
+    ]"#;
+
+    const PHOTON_SEARCH: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [2.3483915, 48.8534951] },
+                "properties": {
+                    "name": "Paris",
+                    "city": "Paris",
+                    "state": "Île-de-France",
+                    "country": "France",
+                    "countrycode": "FR",
+                    "type": "city",
+                    "osm_value": "city",
+                    "extent": [2.224122, 48.902156, 2.4697602, 48.8155755]
+                }
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [13.3777, 52.5163] },
+                "properties": {
+                    "street": "Pariser Platz",
+                    "housenumber": "1",
+                    "city": "Berlin",
+                    "country": "Germany",
+                    "osm_value": "house"
+                }
+            }
+        ]
+    }"#;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn parses_nominatim_search_results() {
+        let geocoder = NominatimGeocoder::public(NOMINATIM_PUBLIC_ENDPOINT.to_string());
+
+        let candidates = geocoder
+            .parse_search_response(NOMINATIM_SEARCH.as_bytes())
+            .unwrap();
+
+        assert_eq!(candidates.len(), 2);
+        let berlin = &candidates[0];
+        assert_eq!(berlin.display_name, "Berlin, Deutschland");
+        assert_eq!(berlin.place_type, "city");
+        assert_eq!(berlin.country_code.as_deref(), Some("DE"));
+        assert_close(berlin.latitude, 52.5170365);
+        assert_close(berlin.longitude, 13.3888599);
+        let bounding_box = berlin.bounding_box.as_ref().unwrap();
+        assert_close(bounding_box.south, 52.3382448);
+        assert_close(bounding_box.north, 52.6755087);
+        assert_close(bounding_box.west, 13.088345);
+        assert_close(bounding_box.east, 13.7611609);
+
+        let town = &candidates[1];
+        assert_eq!(town.place_type, "town");
+        assert_eq!(town.country_code, None);
+        assert!(town.bounding_box.is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_nominatim_results() {
+        let geocoder = NominatimGeocoder::public(NOMINATIM_PUBLIC_ENDPOINT.to_string());
+
+        let bad_latitude = r#"[{ "lat": "north", "lon": "13.4", "display_name": "Berlin" }]"#;
+        let error = geocoder
+            .parse_search_response(bad_latitude.as_bytes())
+            .unwrap_err();
+        assert_eq!(error.code, "errors.geocode.latitude_parse_failed");
+
+        let error = geocoder.parse_search_response(b"<html>").unwrap_err();
+        assert_eq!(error.code, "errors.geocode.parse_failed");
+    }
+
+    #[test]
+    fn parses_nominatim_reverse_results() {
+        let geocoder = NominatimGeocoder::self_hosted("http://localhost:8080".to_string());
+
+        let item = r#"{ "lat": "48.1374", "lon": "11.5755", "display_name": "München, Bayern" }"#;
+        let candidate = geocoder
+            .parse_reverse_response(item.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(candidate.display_name, "München, Bayern");
+
+        let open_sea = r#"{ "error": "Unable to geocode" }"#;
+        assert!(geocoder
+            .parse_reverse_response(open_sea.as_bytes())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn parses_photon_search_results() {
+        let geocoder = PhotonGeocoder::new(PHOTON_PUBLIC_ENDPOINT.to_string());
+
+        let candidates = geocoder
+            .parse_search_response(PHOTON_SEARCH.as_bytes())
+            .unwrap();
+
+        assert_eq!(candidates.len(), 2);
+        let paris = &candidates[0];
+        assert_eq!(paris.display_name, "Paris, Île-de-France, France");
+        assert_eq!(paris.place_type, "city");
+        assert_eq!(paris.country_code.as_deref(), Some("FR"));
+        assert_close(paris.latitude, 48.8534951);
+        assert_close(paris.longitude, 2.3483915);
+        let bounding_box = paris.bounding_box.as_ref().unwrap();
+        assert_close(bounding_box.west, 2.224122);
+        assert_close(bounding_box.north, 48.902156);
+        assert_close(bounding_box.east, 2.4697602);
+        assert_close(bounding_box.south, 48.8155755);
+
+        let house = &candidates[1];
+        assert_eq!(house.display_name, "Pariser Platz 1, Berlin, Germany");
+        assert_eq!(house.place_type, "house");
+        assert!(house.bounding_box.is_none());
+    }
+
+    #[test]
+    fn parses_photon_reverse_results() {
+        let geocoder = PhotonGeocoder::new("http://localhost:2322".to_string());
+
+        let candidate = geocoder
+            .parse_reverse_response(PHOTON_SEARCH.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(candidate.display_name, "Paris, Île-de-France, France");
+
+        let empty = r#"{ "type": "FeatureCollection", "features": [] }"#;
+        assert!(geocoder
+            .parse_reverse_response(empty.as_bytes())
+            .unwrap()
+            .is_none());
+
+        let no_coordinates = r#"{ "features": [{ "geometry": { "coordinates": [] } }] }"#;
+        let error = geocoder
+            .parse_reverse_response(no_coordinates.as_bytes())
+            .unwrap_err();
+        assert_eq!(error.code, "errors.geocode.parse_failed");
+    }
+
+    #[test]
+    fn throttles_only_public_endpoints() {
+        let cases: [(Box<dyn Geocoder>, Duration); 5] = [
+            (
+                Box::new(NominatimGeocoder::public(
+                    NOMINATIM_PUBLIC_ENDPOINT.to_string(),
+                )),
+                NOMINATIM_PUBLIC_MIN_INTERVAL,
+            ),
+            (
+                Box::new(NominatimGeocoder::self_hosted(
+                    "http://localhost:8080".to_string(),
+                )),
+                Duration::ZERO,
+            ),
+            (
+                Box::new(PhotonGeocoder::new(PHOTON_PUBLIC_ENDPOINT.to_string())),
+                PHOTON_PUBLIC_MIN_INTERVAL,
+            ),
+            (
+                Box::new(PhotonGeocoder::new("http://localhost:2322".to_string())),
+                Duration::ZERO,
+            ),
+            (
+                Box::new(PhotonGeocoder::new(
+                    "https://photon.komoot.io.example.com".to_string(),
+                )),
+                Duration::ZERO,
+            ),
+        ];
+
+        for (geocoder, expected) in cases {
+            assert_eq!(
+                geocoder.min_request_interval(),
+                expected,
+                "{}",
+                geocoder.endpoint()
+            );
+        }
+    }
+}
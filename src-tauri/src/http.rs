@@ -0,0 +1,359 @@
+use crate::models::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, ACCEPT_LANGUAGE, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::sleep;
+
+const USER_AGENT: &str = concat!(
+    "WinLux/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/Lparksi/WinLux)"
+);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+/// Servers asking us to wait longer than this are reported as rate limiting instead of
+/// leaving the caller hanging.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+const ERROR_BODY_MAX_CHARS: usize = 200;
+
+static HTTP_CLIENT: Mutex<Option<reqwest::Client>> = Mutex::new(None);
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Returns the process-wide client so connections and TLS sessions are reused between
+/// requests.
+pub fn shared_client() -> AppResult<reqwest::Client> {
+    let mut guard = match HTTP_CLIENT.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    if let Some(client) = guard.as_ref() {
+        return Ok(client.clone());
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .no_proxy();
+    if let Some(proxy) = crate::proxy::resolve_proxy()?.proxy {
+        builder = builder.proxy(proxy);
+    }
+
+    let client = builder
+        .build()
+        .map_err(|error| err_with_source("errors.network.client_build_failed", error))?;
+    *guard = Some(client.clone());
+
+    Ok(client)
+}
+
+/// Drops the cached client so the next request picks up changed proxy settings.
+pub fn reset_shared_client() {
+    let mut guard = match HTTP_CLIENT.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    *guard = None;
+}
+
+/// Sends a single GET request without retries and returns the response status.
+pub async fn probe(url: &str) -> AppResult<u16> {
+    let client = shared_client()?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|error| request_error(error, &host_of(url)))?;
+
+    Ok(response.status().as_u16())
+}
+
+/// Sends a GET request and returns the body of the first successful response. Asks for
+/// text in `language`, a BCP 47 tag, through `Accept-Language`.
+///
+/// Timeouts, connection failures, 429 and 502-504 responses are retried with exponential
+/// backoff, honouring `Retry-After` when the server sends one. `before_attempt` runs ahead
+/// of every attempt, retries included, so callers can apply their own rate limit.
+pub async fn get_bytes<F, Fut>(
+    url: &str,
+    query: &[(&'static str, String)],
+    language: &str,
+    mut before_attempt: F,
+) -> AppResult<Vec<u8>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let client = shared_client()?;
+    let host = host_of(url);
+    let accept_language = accept_language(language);
+
+    let mut attempt = 1;
+    loop {
+        before_attempt().await;
+
+        let request = client
+            .get(url)
+            .query(query)
+            .header(ACCEPT_LANGUAGE, &accept_language);
+        let (error, retry_after) = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                return response
+                    .bytes()
+                    .await
+                    .map(|body| body.to_vec())
+                    .map_err(|error| request_error(error, &host));
+            }
+            Ok(response) => {
+                let status = response.status();
+                let retry_after = retry_after(response.headers());
+                let body = response.text().await.unwrap_or_default();
+                let error = status_error(status, &body, &host, retry_after);
+                if !is_retryable_status(status) {
+                    return Err(error);
+                }
+                (error, retry_after)
+            }
+            Err(error) if error.is_timeout() || error.is_connect() => {
+                (request_error(error, &host), None)
+            }
+            Err(error) => return Err(request_error(error, &host)),
+        };
+
+        if attempt >= MAX_ATTEMPTS || retry_after.is_some_and(|delay| delay > MAX_RETRY_AFTER) {
+            return Err(error);
+        }
+
+        sleep(retry_after.unwrap_or_default().max(backoff_delay(attempt))).await;
+        attempt += 1;
+    }
+}
+
+/// Lists the bare language after the regional tag so that services with labels only for
+/// `ja` still answer `ja-JP` in Japanese.
+fn accept_language(language: &str) -> String {
+    match language.split_once('-') {
+        Some((primary, _)) => format!("{language},{primary};q=0.9"),
+        None => language.to_string(),
+    }
+}
+
+/// Failures where offline data is a better answer than an error: the request never
+/// completed, or the service is throttling us or unavailable.
+pub fn is_network_failure(error: &AppError) -> bool {
+    match error.code.as_str() {
+        "errors.network.client_build_failed"
+        | "errors.network.timeout"
+        | "errors.network.offline"
+        | "errors.network.rate_limited"
+        | "errors.network.request_failed" => true,
+        "errors.network.http_failed" => error
+            .params
+            .get("status")
+            .and_then(|status| status.parse::<u16>().ok())
+            .is_some_and(|status| status >= 500),
+        _ => false,
+    }
+}
+
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn request_error(error: reqwest::Error, host: &str) -> AppError {
+    let code = if error.is_timeout() {
+        "errors.network.timeout"
+    } else if error.is_connect() {
+        "errors.network.offline"
+    } else {
+        "errors.network.request_failed"
+    };
+
+    err_with_source(code, error.without_url()).with_param("host", host)
+}
+
+fn status_error(
+    status: StatusCode,
+    body: &str,
+    host: &str,
+    retry_after: Option<Duration>,
+) -> AppError {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let error = err("errors.network.rate_limited").with_param("host", host);
+        return match retry_after {
+            Some(delay) => error.with_param("retry_after_seconds", delay.as_secs()),
+            None => error,
+        };
+    }
+
+    err("errors.network.http_failed")
+        .with_param("host", host)
+        .with_param("status", status.as_u16())
+        .with_param("body", sanitize_body(body))
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// Accepts both forms allowed by RFC 9110: delay seconds and an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = retry_at.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Error pages can be large HTML documents; keep only a short single-line text excerpt.
+/// Bytes that weren't valid in the response's charset are dropped rather than shown as
+/// replacement characters.
+fn sanitize_body(body: &str) -> String {
+    let mut text = String::with_capacity(body.len().min(ERROR_BODY_MAX_CHARS * 4));
+    let mut in_tag = false;
+
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if in_tag => {}
+            char::REPLACEMENT_CHARACTER => {}
+            c if c.is_control() || c.is_whitespace() => text.push(' '),
+            c => text.push(c),
+        }
+    }
+
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= ERROR_BODY_MAX_CHARS {
+        return collapsed;
+    }
+
+    let mut truncated: String = collapsed.chars().take(ERROR_BODY_MAX_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers_with_retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn reads_retry_after_seconds() {
+        assert_eq!(
+            retry_after(&headers_with_retry_after("120")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&headers_with_retry_after("0")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+        assert_eq!(retry_after(&headers_with_retry_after("-5")), None);
+        assert_eq!(retry_after(&headers_with_retry_after("soon")), None);
+    }
+
+    #[test]
+    fn reads_retry_after_http_dates() {
+        let in_a_minute = (Utc::now() + chrono::Duration::seconds(60))
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        let delay = retry_after(&headers_with_retry_after(&in_a_minute)).unwrap();
+        assert!(
+            delay > Duration::from_secs(55) && delay <= Duration::from_secs(60),
+            "{delay:?}"
+        );
+
+        assert_eq!(
+            retry_after(&headers_with_retry_after("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn doubles_the_backoff_up_to_the_cap() {
+        let cases = [
+            (1, Duration::from_millis(500)),
+            (2, Duration::from_secs(1)),
+            (3, Duration::from_secs(2)),
+            (5, Duration::from_secs(8)),
+            (6, MAX_BACKOFF),
+            (64, MAX_BACKOFF),
+        ];
+
+        for (attempt, expected) in cases {
+            assert_eq!(backoff_delay(attempt), expected, "attempt {attempt}");
+        }
+    }
+
+    #[test]
+    fn reduces_error_pages_to_one_line_of_text() {
+        let page = "<html>\n<head><title>502 Bad Gateway</title></head>\n<body>\t<h1>Bad\r\nGateway</h1></body></html>";
+
+        assert_eq!(sanitize_body(page), "502 Bad Gateway Bad Gateway");
+        assert_eq!(sanitize_body(""), "");
+    }
+
+    #[test]
+    fn drops_bytes_that_are_not_text() {
+        // What `Response::text` makes of a body that isn't valid UTF-8.
+        let body = String::from_utf8_lossy(b"Service \xff\xfeunavailable\x00");
+
+        assert_eq!(sanitize_body(&body), "Service unavailable");
+    }
+
+    #[test]
+    fn truncates_long_bodies() {
+        let body = "é".repeat(ERROR_BODY_MAX_CHARS * 3);
+
+        let excerpt = sanitize_body(&body);
+
+        assert_eq!(excerpt.chars().count(), ERROR_BODY_MAX_CHARS + 1);
+        assert!(excerpt.starts_with("éé"));
+        assert!(excerpt.ends_with('…'));
+    }
+}
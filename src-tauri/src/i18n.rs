@@ -1,14 +1,11 @@
 use crate::models::{AppError, AppResult, LanguageSettings};
 use std::collections::HashMap;
 use std::sync::OnceLock;
-use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+use windows_sys::Win32::Globalization::{GetUserDefaultGeoName, GetUserDefaultLocaleName};
 
 pub const LANGUAGE_CHANGED_EVENT: &str = "language-changed";
 pub const LANGUAGE_PREFERENCE_AUTO: &str = "auto";
 
-const SETTINGS_KEY: &str = "Software\\WinLux";
-const SETTINGS_VALUE_LANGUAGE_PREFERENCE: &str = "LanguagePreference";
-
 static SHARED_MESSAGES_BY_LOCALE: OnceLock<HashMap<&'static str, HashMap<String, String>>> =
     OnceLock::new();
 static TRAY_TEXTS: OnceLock<HashMap<String, TrayTextsOwned>> = OnceLock::new();
@@ -79,51 +76,33 @@ pub fn get_language_settings() -> LanguageSettings {
     }
 }
 
-fn err_with_source(code: &str, source: impl ToString) -> AppError {
-    AppError::new(code).with_param("source", source.to_string())
-}
-
 pub fn set_language_preference(preference: &str) -> AppResult<()> {
-    let normalized_preference = if preference.eq_ignore_ascii_case(LANGUAGE_PREFERENCE_AUTO) {
-        LANGUAGE_PREFERENCE_AUTO.to_string()
-    } else {
-        canonicalize_language(preference)
-            .ok_or_else(|| {
-                AppError::new("errors.language.unsupported")
-                    .with_param("preference", preference)
-            })?
-            .to_string()
-    };
+    let normalized_preference = normalize_language_preference(preference)?;
 
-    use winreg::enums::HKEY_CURRENT_USER;
-    use winreg::RegKey;
-
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu
-        .create_subkey(SETTINGS_KEY)
-        .map_err(|error| err_with_source("errors.registry.create_settings_failed", error))?;
-
-    key.set_value(
-        SETTINGS_VALUE_LANGUAGE_PREFERENCE,
-        &normalized_preference.as_str(),
-    )
-    .map_err(|error| err_with_source("errors.language.preference_write_failed", error))?;
+    crate::settings::update(|settings| {
+        settings.language_preference = normalized_preference;
+        Ok(())
+    })?;
 
     Ok(())
 }
 
-pub fn get_language_preference() -> String {
-    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ};
-    use winreg::RegKey;
+/// Maps a preference to its canonical spelling, rejecting languages WinLux doesn't ship.
+pub fn normalize_language_preference(preference: &str) -> AppResult<String> {
+    if preference.eq_ignore_ascii_case(LANGUAGE_PREFERENCE_AUTO) {
+        return Ok(LANGUAGE_PREFERENCE_AUTO.to_string());
+    }
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let key = match hkcu.open_subkey_with_flags(SETTINGS_KEY, KEY_READ) {
-        Ok(key) => key,
-        Err(_) => return LANGUAGE_PREFERENCE_AUTO.to_string(),
-    };
+    canonicalize_language(preference)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            AppError::new("errors.language.unsupported").with_param("preference", preference)
+        })
+}
 
-    let stored_preference: String = match key.get_value(SETTINGS_VALUE_LANGUAGE_PREFERENCE) {
-        Ok(value) => value,
+pub fn get_language_preference() -> String {
+    let stored_preference = match crate::settings::load() {
+        Ok(settings) => settings.language_preference,
         Err(_) => return LANGUAGE_PREFERENCE_AUTO.to_string(),
     };
 
@@ -144,6 +123,11 @@ pub fn resolve_language(preference: &str) -> &'static str {
     canonicalize_language(preference).unwrap_or("English")
 }
 
+/// Locale tag of the language the UI is currently shown in, e.g. `de-DE`.
+pub fn current_shared_locale() -> &'static str {
+    shared_locale_for_language(resolve_language(&get_language_preference()))
+}
+
 pub fn tray_texts(language: &str) -> TrayTexts {
     let texts_map = tray_texts_map();
     let key = canonicalize_language(language).unwrap_or("English");
@@ -208,6 +192,14 @@ pub fn tray_sunset_offset_custom_label(language: &str, minutes: i64) -> String {
     )
 }
 
+pub fn tray_locations_menu_label(language: &str) -> String {
+    translate_shared(language, "tray.locations.menu")
+}
+
+pub fn tray_locations_empty_label(language: &str) -> String {
+    translate_shared(language, "tray.locations.empty")
+}
+
 fn translate_shared(language: &str, key: &str) -> String {
     let locale_messages = shared_messages(language);
     let fallback_messages = shared_messages_for_locale("en-US");
@@ -488,6 +480,42 @@ fn get_system_locale_name() -> Option<String> {
     }
 }
 
+/// ISO 3166-1 alpha-2 code of the Windows region setting, falling back to the region
+/// subtag of the user locale.
+pub fn system_region_code() -> Option<String> {
+    get_user_geo_name()
+        .or_else(|| get_system_locale_name().and_then(|locale| region_from_locale(&locale)))
+}
+
+fn get_user_geo_name() -> Option<String> {
+    let mut geo_buffer = [0u16; 16];
+    let geo_len =
+        unsafe { GetUserDefaultGeoName(geo_buffer.as_mut_ptr(), geo_buffer.len() as i32) };
+
+    if geo_len <= 1 {
+        return None;
+    }
+
+    let geo_name = String::from_utf16_lossy(&geo_buffer[..(geo_len as usize - 1)]);
+    normalize_region_code(&geo_name)
+}
+
+fn region_from_locale(locale: &str) -> Option<String> {
+    locale
+        .split(['-', '_'])
+        .skip(1)
+        .find_map(normalize_region_code)
+}
+
+fn normalize_region_code(code: &str) -> Option<String> {
+    let trimmed = code.trim();
+    if trimmed.len() == 2 && trimmed.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(trimmed.to_ascii_uppercase())
+    } else {
+        None
+    }
+}
+
 fn map_locale_to_language(locale: &str) -> &'static str {
     let normalized = locale.trim().replace('_', "-").to_lowercase();
 
@@ -0,0 +1,506 @@
+use crate::models::{
+    AppError, AppResult, GeocodeResult, IpLocationSettings, IpLocationSettingsPatch,
+    LocationAutoUpdateSettings, LocationAutoUpdateSettingsPatch, LocationSource, SolarSettings,
+    StoredIpLocationSettings,
+};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use tokio::time::timeout;
+
+pub const IP_LOCATION_SETTINGS_CHANGED_EVENT: &str = "ip-location-settings-changed";
+const IP_LOCATION_DEFAULT_ENDPOINT: &str = "https://ipapi.co/json/";
+// Field names differ between services (ipapi.co, ipwho.is, ip-api.com, ...), so the
+// response is read by trying each known spelling in turn.
+const LATITUDE_FIELDS: [&str; 2] = ["latitude", "lat"];
+const LONGITUDE_FIELDS: [&str; 3] = ["longitude", "lon", "lng"];
+const CITY_FIELDS: [&str; 1] = ["city"];
+const REGION_FIELDS: [&str; 3] = ["region_name", "regionName", "region"];
+const COUNTRY_CODE_FIELDS: [&str; 2] = ["country_code", "countryCode"];
+const ERROR_MESSAGE_FIELDS: [&str; 3] = ["reason", "message", "error"];
+pub const LOCATION_AUTO_UPDATE_SETTINGS_CHANGED_EVENT: &str =
+    "location-auto-update-settings-changed";
+const LOCATION_AUTO_UPDATE_DEFAULT_INTERVAL_HOURS: u32 = 6;
+const LOCATION_AUTO_UPDATE_INTERVAL_HOURS_RANGE: (u32, u32) = (1, 168);
+const LOCATION_AUTO_UPDATE_DEFAULT_MIN_DISTANCE_KM: u32 = 25;
+const LOCATION_AUTO_UPDATE_MIN_DISTANCE_KM_RANGE: (u32, u32) = (1, 1000);
+/// The worker sleeps this long while auto-update is off; enabling it wakes the worker.
+const LOCATION_AUTO_UPDATE_IDLE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// After a failed check (access denied, no fix in time), retry well before the interval.
+const LOCATION_AUTO_UPDATE_ERROR_RETRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// `<latitude>,<longitude>` that replaces the OS location service, for exercising
+/// auto-update on machines without one.
+const MOCK_LOCATION_ENV: &str = "WINLUX_MOCK_LOCATION";
+
+static LOCATION_AUTO_UPDATE_WORKER_STARTED: OnceLock<()> = OnceLock::new();
+static LOCATION_AUTO_UPDATE_WAKE_SIGNAL: OnceLock<Notify> = OnceLock::new();
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+pub type LocateFuture<'a> = Pin<Box<dyn Future<Output = AppResult<GeocodeResult>> + Send + 'a>>;
+
+/// A source that can tell where this machine is without the user typing an address.
+pub trait LocationProvider: Send + Sync {
+    fn locate(&self) -> LocateFuture<'_>;
+}
+
+/// Looks up the public IP address with a JSON geolocation service. The result is only as
+/// precise as the service's database, usually the city of the ISP's point of presence.
+pub struct IpLocationProvider {
+    endpoint: String,
+}
+
+impl IpLocationProvider {
+    pub fn new(endpoint: String) -> Self {
+        Self { endpoint }
+    }
+}
+
+impl LocationProvider for IpLocationProvider {
+    fn locate(&self) -> LocateFuture<'_> {
+        Box::pin(async move {
+            let language = crate::i18n::current_shared_locale();
+            let body = crate::http::get_bytes(&self.endpoint, &[], language, || async {}).await?;
+            parse_ip_location(&body)
+        })
+    }
+}
+
+/// The Windows location service (`Windows.Devices.Geolocation`), which combines GPS,
+/// Wi-Fi and cell positioning as available. Subject to the user's location privacy setting.
+pub struct WindowsLocationProvider;
+
+impl LocationProvider for WindowsLocationProvider {
+    fn locate(&self) -> LocateFuture<'_> {
+        Box::pin(async {
+            let (latitude, longitude) = tauri::async_runtime::spawn_blocking(read_windows_position)
+                .await
+                .map_err(|error| {
+                    err_with_source("errors.location_provider.unavailable", error)
+                })??;
+
+            Ok(device_location(latitude, longitude))
+        })
+    }
+}
+
+/// Reports a fixed position.
+pub struct MockLocationProvider {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl MockLocationProvider {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    fn from_env() -> Option<Self> {
+        let value = std::env::var(MOCK_LOCATION_ENV).ok()?;
+        let (latitude, longitude) = crate::coordinates::parse_coordinates(&value)?;
+        Some(Self::new(latitude, longitude))
+    }
+}
+
+impl LocationProvider for MockLocationProvider {
+    fn locate(&self) -> LocateFuture<'_> {
+        Box::pin(async move { Ok(device_location(self.latitude, self.longitude)) })
+    }
+}
+
+pub fn device_location_provider() -> Box<dyn LocationProvider> {
+    match MockLocationProvider::from_env() {
+        Some(mock) => Box::new(mock),
+        None => Box::new(WindowsLocationProvider),
+    }
+}
+
+pub fn start_location_auto_update_worker(app: AppHandle) {
+    if LOCATION_AUTO_UPDATE_WORKER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    let wake_signal = LOCATION_AUTO_UPDATE_WAKE_SIGNAL.get_or_init(Notify::new);
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait_duration = run_location_auto_update(&app).await;
+            let _ = timeout(wait_duration, wake_signal.notified()).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_location_auto_update_settings() -> AppResult<LocationAutoUpdateSettings> {
+    Ok(read_location_auto_update_settings())
+}
+
+#[tauri::command]
+pub fn set_location_auto_update_settings(
+    app: AppHandle,
+    enabled: bool,
+    interval_hours: u32,
+    min_distance_km: u32,
+) -> AppResult<LocationAutoUpdateSettings> {
+    validate_location_auto_update_range(interval_hours, min_distance_km)?;
+    write_location_auto_update_settings(enabled, interval_hours, min_distance_km)?;
+    notify_location_auto_update_worker();
+
+    let settings = read_location_auto_update_settings();
+    let _ = app.emit(LOCATION_AUTO_UPDATE_SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
+
+pub(crate) fn notify_location_auto_update_worker() {
+    if let Some(signal) = LOCATION_AUTO_UPDATE_WAKE_SIGNAL.get() {
+        signal.notify_one();
+    }
+}
+
+#[tauri::command]
+pub fn get_ip_location_settings() -> AppResult<IpLocationSettings> {
+    Ok(read_ip_location_settings())
+}
+
+#[tauri::command]
+pub fn set_ip_location_settings(
+    app: AppHandle,
+    enabled: bool,
+    endpoint: String,
+) -> AppResult<IpLocationSettings> {
+    let endpoint = endpoint.trim().to_string();
+    normalize_endpoint(&endpoint)?;
+    write_ip_location_settings(enabled, &endpoint)?;
+
+    let settings = read_ip_location_settings();
+    let _ = app.emit(IP_LOCATION_SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
+
+/// Saves the approximate location of the public IP address as the solar location.
+#[tauri::command]
+pub async fn detect_approximate_location(app: AppHandle) -> AppResult<SolarSettings> {
+    let settings = read_ip_location_settings();
+    if !settings.enabled {
+        return Err(err("errors.ip_location.disabled"));
+    }
+
+    let provider = IpLocationProvider::new(normalize_endpoint(&settings.endpoint)?);
+    let location = provider.locate().await?;
+    crate::locations::save_solar_location_for_app(&app, &location)?;
+
+    crate::commands::finish_solar_location_change(&app)
+}
+
+/// Checks the device position when the interval has elapsed and returns how long to wait
+/// before the next check.
+async fn run_location_auto_update(app: &AppHandle) -> Duration {
+    let settings = read_location_auto_update_settings();
+    if !settings.enabled {
+        return LOCATION_AUTO_UPDATE_IDLE_INTERVAL;
+    }
+
+    let interval = Duration::from_secs(u64::from(settings.interval_hours) * 60 * 60);
+    let since_last_check = settings
+        .last_checked_at
+        .as_deref()
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .and_then(|checked_at| (Utc::now() - checked_at.with_timezone(&Utc)).to_std().ok());
+    if let Some(elapsed) = since_last_check.filter(|elapsed| *elapsed < interval) {
+        return interval - elapsed;
+    }
+
+    if refresh_location_from_device(app, settings.min_distance_km)
+        .await
+        .is_err()
+    {
+        return LOCATION_AUTO_UPDATE_ERROR_RETRY_INTERVAL.min(interval);
+    }
+
+    let _ = write_location_auto_update_last_check(&Utc::now().to_rfc3339());
+    interval
+}
+
+/// Saves the device position as the solar location unless it is within
+/// `min_distance_km` of the current one. Returns whether the location changed.
+async fn refresh_location_from_device(app: &AppHandle, min_distance_km: u32) -> AppResult<bool> {
+    let mut location = device_location_provider().locate().await?;
+
+    let settings = crate::commands::get_solar_settings()?;
+    if let Some(current) = settings.location {
+        let moved_km = crate::offline_geocoder::distance_km(
+            current.latitude,
+            current.longitude,
+            location.latitude,
+            location.longitude,
+        );
+        if moved_km < f64::from(min_distance_km) {
+            return Ok(false);
+        }
+    }
+
+    if let Ok(candidate) =
+        crate::commands::reverse_geocode_internal(location.latitude, location.longitude).await
+    {
+        location.display_name = candidate.display_name;
+    }
+
+    crate::locations::save_solar_location_for_app(app, &location)?;
+    crate::commands::finish_solar_location_change(app)?;
+    Ok(true)
+}
+
+/// Blocks until Windows reports a position, so it must run off the async runtime.
+fn read_windows_position() -> AppResult<(f64, f64)> {
+    use windows::Devices::Geolocation::{GeolocationAccessStatus, Geolocator};
+
+    let unavailable = |error: windows::core::Error| {
+        err_with_source("errors.location_provider.unavailable", error)
+    };
+
+    let access = Geolocator::RequestAccessAsync()
+        .and_then(|operation| operation.get())
+        .map_err(unavailable)?;
+    if access != GeolocationAccessStatus::Allowed {
+        return Err(err("errors.location_provider.access_denied"));
+    }
+
+    let position = Geolocator::new()
+        .and_then(|locator| locator.GetGeopositionAsync())
+        .and_then(|operation| operation.get())
+        .and_then(|geoposition| geoposition.Coordinate())
+        .and_then(|coordinate| coordinate.Point())
+        .and_then(|point| point.Position())
+        .map_err(unavailable)?;
+
+    Ok((position.Latitude, position.Longitude))
+}
+
+fn device_location(latitude: f64, longitude: f64) -> GeocodeResult {
+    let label = crate::coordinates::format_coordinates(latitude, longitude);
+    GeocodeResult {
+        address: label.clone(),
+        display_name: label,
+        latitude,
+        longitude,
+        source: LocationSource::Device,
+    }
+}
+
+fn parse_ip_location(body: &[u8]) -> AppResult<GeocodeResult> {
+    let value: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|error| err_with_source("errors.ip_location.parse_failed", error))?;
+
+    let (Some(latitude), Some(longitude)) = (
+        first_number(&value, &LATITUDE_FIELDS),
+        first_number(&value, &LONGITUDE_FIELDS),
+    ) else {
+        // Services report quota and lookup failures as JSON without coordinates.
+        let reason = first_string(&value, &ERROR_MESSAGE_FIELDS).unwrap_or_default();
+        return Err(err_with_source("errors.ip_location.parse_failed", reason));
+    };
+    crate::coordinates::validate_coordinates(latitude, longitude)?;
+
+    let city = first_string(&value, &CITY_FIELDS);
+    let region =
+        first_string(&value, &REGION_FIELDS).filter(|region| Some(region) != city.as_ref());
+    let country_code =
+        first_string(&value, &COUNTRY_CODE_FIELDS).map(|code| code.to_ascii_uppercase());
+    let display_name = [city, region, country_code]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+    let display_name = if display_name.is_empty() {
+        crate::coordinates::format_coordinates(latitude, longitude)
+    } else {
+        display_name
+    };
+
+    Ok(GeocodeResult {
+        address: display_name.clone(),
+        display_name,
+        latitude,
+        longitude,
+        source: LocationSource::IpApproximate,
+    })
+}
+
+fn first_number(value: &serde_json::Value, fields: &[&str]) -> Option<f64> {
+    fields.iter().find_map(|field| match value.get(field)? {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn first_string(value: &serde_json::Value, fields: &[&str]) -> Option<String> {
+    fields
+        .iter()
+        .filter_map(|field| value.get(field)?.as_str())
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn normalize_endpoint(endpoint: &str) -> AppResult<String> {
+    let trimmed = endpoint.trim();
+    let effective = if trimmed.is_empty() {
+        IP_LOCATION_DEFAULT_ENDPOINT
+    } else {
+        trimmed
+    };
+
+    let url = reqwest::Url::parse(effective).map_err(|error| {
+        err_with_source("errors.ip_location.invalid_endpoint", error)
+            .with_param("endpoint", effective)
+    })?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(err("errors.ip_location.invalid_endpoint")
+            .with_param("endpoint", effective)
+            .with_param("source", url.scheme()));
+    }
+
+    Ok(effective.to_string())
+}
+
+pub(crate) fn validate_ip_location_settings(stored: &StoredIpLocationSettings) -> AppResult<()> {
+    normalize_endpoint(&stored.endpoint)?;
+    Ok(())
+}
+
+pub(crate) fn validate_location_auto_update_settings(
+    settings: &LocationAutoUpdateSettings,
+) -> AppResult<()> {
+    validate_location_auto_update_range(settings.interval_hours, settings.min_distance_km)
+}
+
+pub(crate) fn apply_ip_location_patch(
+    stored: &mut StoredIpLocationSettings,
+    patch: &IpLocationSettingsPatch,
+) -> AppResult<()> {
+    if let Some(enabled) = patch.enabled {
+        stored.enabled = enabled;
+    }
+    if let Some(endpoint) = &patch.endpoint {
+        stored.endpoint = endpoint.trim().to_string();
+    }
+
+    validate_ip_location_settings(stored)
+}
+
+pub(crate) fn apply_location_auto_update_patch(
+    settings: &mut LocationAutoUpdateSettings,
+    patch: &LocationAutoUpdateSettingsPatch,
+) -> AppResult<()> {
+    if let Some(enabled) = patch.enabled {
+        settings.enabled = enabled;
+    }
+    if let Some(interval_hours) = patch.interval_hours {
+        settings.interval_hours = interval_hours;
+    }
+    if let Some(min_distance_km) = patch.min_distance_km {
+        settings.min_distance_km = min_distance_km;
+    }
+
+    validate_location_auto_update_settings(settings)
+}
+
+fn validate_location_auto_update_range(interval_hours: u32, min_distance_km: u32) -> AppResult<()> {
+    let (min_interval, max_interval) = LOCATION_AUTO_UPDATE_INTERVAL_HOURS_RANGE;
+    if !(min_interval..=max_interval).contains(&interval_hours) {
+        return Err(err("errors.location_auto_update.invalid_interval")
+            .with_param("min", min_interval)
+            .with_param("max", max_interval)
+            .with_param("value", interval_hours));
+    }
+
+    let (min_distance, max_distance) = LOCATION_AUTO_UPDATE_MIN_DISTANCE_KM_RANGE;
+    if !(min_distance..=max_distance).contains(&min_distance_km) {
+        return Err(err("errors.location_auto_update.invalid_distance")
+            .with_param("min", min_distance)
+            .with_param("max", max_distance)
+            .with_param("value", min_distance_km));
+    }
+
+    Ok(())
+}
+
+fn read_ip_location_settings() -> IpLocationSettings {
+    let stored = crate::settings::load()
+        .map(|settings| settings.ip_location)
+        .unwrap_or_default();
+
+    IpLocationSettings {
+        enabled: stored.enabled,
+        endpoint: stored.endpoint,
+        default_endpoint: IP_LOCATION_DEFAULT_ENDPOINT.to_string(),
+    }
+}
+
+fn write_ip_location_settings(enabled: bool, endpoint: &str) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        settings.ip_location = StoredIpLocationSettings {
+            enabled,
+            endpoint: endpoint.to_string(),
+        };
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+impl Default for LocationAutoUpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: LOCATION_AUTO_UPDATE_DEFAULT_INTERVAL_HOURS,
+            min_distance_km: LOCATION_AUTO_UPDATE_DEFAULT_MIN_DISTANCE_KM,
+            last_checked_at: None,
+        }
+    }
+}
+
+fn read_location_auto_update_settings() -> LocationAutoUpdateSettings {
+    crate::settings::load()
+        .map(|settings| settings.location_auto_update)
+        .unwrap_or_default()
+}
+
+fn write_location_auto_update_settings(
+    enabled: bool,
+    interval_hours: u32,
+    min_distance_km: u32,
+) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        let auto_update = &mut settings.location_auto_update;
+        auto_update.enabled = enabled;
+        auto_update.interval_hours = interval_hours;
+        auto_update.min_distance_km = min_distance_km;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+fn write_location_auto_update_last_check(checked_at: &str) -> AppResult<()> {
+    crate::settings::update(|settings| {
+        settings.location_auto_update.last_checked_at = Some(checked_at.to_string());
+        Ok(())
+    })?;
+
+    Ok(())
+}
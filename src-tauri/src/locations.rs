@@ -0,0 +1,521 @@
+use crate::models::{
+    AppError, AppResult, AppSettings, GeocodeResult, LocationSource, NetworkRule, NetworkRuleKind,
+    SavedLocation, SavedLocations,
+};
+use chrono::Utc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter};
+
+pub const SAVED_LOCATIONS_CHANGED_EVENT: &str = "saved-locations-changed";
+const LOCATION_NAME_MAX_CHARS: usize = 64;
+const NETWORK_RULE_MAX_CHARS: usize = 255;
+
+/// Forward re-geocoding in another language may land on a slightly different point; beyond
+/// this it is probably a different place and the old label is kept.
+const RELOCALIZE_MAX_DRIFT_KM: f64 = 5.0;
+
+static LABEL_BACKFILL_RUNNING: AtomicBool = AtomicBool::new(false);
+static RELOCALIZE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+#[tauri::command]
+pub fn get_saved_locations() -> AppResult<SavedLocations> {
+    read_saved_locations()
+}
+
+#[tauri::command]
+pub async fn add_saved_location(
+    app: AppHandle,
+    name: String,
+    address: String,
+) -> AppResult<SavedLocations> {
+    let name = normalize_location_name(&name)?;
+    let geocode = crate::commands::geocode_address_internal(&address).await?;
+    add_saved_location_for_app(&app, name, geocode)
+}
+
+pub(crate) fn add_saved_location_for_app(
+    app: &AppHandle,
+    name: String,
+    location: GeocodeResult,
+) -> AppResult<SavedLocations> {
+    crate::settings::update(|settings| {
+        let saved_locations = &mut settings.saved_locations;
+        let saved = SavedLocation {
+            id: next_location_id(saved_locations),
+            name,
+            location,
+            network_rules: Vec::new(),
+        };
+        saved_locations.locations.push(saved);
+        Ok(())
+    })?;
+
+    let saved_locations = emit_saved_locations_changed(app)?;
+    spawn_location_label_backfill(app.clone());
+    Ok(saved_locations)
+}
+
+#[tauri::command]
+pub fn rename_saved_location(
+    app: AppHandle,
+    id: String,
+    name: String,
+) -> AppResult<SavedLocations> {
+    let name = normalize_location_name(&name)?;
+    crate::settings::update(|settings| {
+        saved_location_mut(settings, &id)?.name = name;
+        Ok(())
+    })?;
+
+    emit_saved_locations_changed(&app)
+}
+
+#[tauri::command]
+pub fn set_saved_location_network_rules(
+    app: AppHandle,
+    id: String,
+    rules: Vec<NetworkRule>,
+) -> AppResult<SavedLocations> {
+    let network_rules = normalize_network_rules(rules)?;
+    crate::settings::update(|settings| {
+        saved_location_mut(settings, &id)?.network_rules = network_rules;
+        Ok(())
+    })?;
+
+    let saved_locations = emit_saved_locations_changed(&app)?;
+    crate::network::reevaluate_network_location();
+    Ok(saved_locations)
+}
+
+#[tauri::command]
+pub fn delete_saved_location(app: AppHandle, id: String) -> AppResult<SavedLocations> {
+    crate::settings::update(|settings| {
+        saved_location_mut(settings, &id)?;
+
+        let saved_locations = &mut settings.saved_locations;
+        saved_locations.locations.retain(|saved| saved.id != id);
+        if saved_locations.active_id.as_deref() == Some(id.as_str()) {
+            saved_locations.active_id = None;
+        }
+        Ok(())
+    })?;
+
+    emit_saved_locations_changed(&app)
+}
+
+#[tauri::command]
+pub fn activate_saved_location(app: AppHandle, id: String) -> AppResult<SavedLocations> {
+    activate_saved_location_for_app(&app, &id)
+}
+
+pub fn activate_saved_location_for_app(app: &AppHandle, id: &str) -> AppResult<SavedLocations> {
+    crate::settings::update(|settings| {
+        let location = saved_location_mut(settings, id)?.location.clone();
+        crate::commands::set_solar_location(settings, &location);
+        settings.saved_locations.active_id = Some(id.to_string());
+        Ok(())
+    })?;
+
+    let saved_locations = emit_saved_locations_changed(app)?;
+    crate::commands::finish_solar_location_change(app)?;
+    Ok(saved_locations)
+}
+
+/// Saves the solar location and, in the same write, the saved locations it affects, so a
+/// failed save can't leave the two disagreeing.
+pub(crate) fn save_solar_location_for_app(
+    app: &AppHandle,
+    location: &GeocodeResult,
+) -> AppResult<()> {
+    let mut saved_locations_changed = false;
+    crate::settings::update(|settings| {
+        saved_locations_changed = set_solar_location(settings, location);
+        Ok(())
+    })?;
+
+    if saved_locations_changed {
+        emit_saved_locations_changed(app)?;
+    }
+    Ok(())
+}
+
+/// A chosen place also moves the active saved location it mirrors. A detected position
+/// only moves the solar location and leaves no saved location active, so a laptop on the
+/// road doesn't rewrite "Office" to wherever it is now. Returns whether saved locations
+/// changed.
+fn set_solar_location(settings: &mut AppSettings, location: &GeocodeResult) -> bool {
+    crate::commands::set_solar_location(settings, location);
+
+    let saved_locations = &mut settings.saved_locations;
+    if is_detected_location(location) {
+        return saved_locations.active_id.take().is_some();
+    }
+
+    let active_id = saved_locations.active_id.as_deref();
+    match saved_locations
+        .locations
+        .iter_mut()
+        .find(|saved| Some(saved.id.as_str()) == active_id)
+    {
+        Some(active) => {
+            active.location = location.clone();
+            true
+        }
+        None => false,
+    }
+}
+
+fn is_detected_location(location: &GeocodeResult) -> bool {
+    matches!(
+        location.source,
+        LocationSource::Device | LocationSource::IpApproximate | LocationSource::TimeZone
+    )
+}
+
+pub fn spawn_location_label_backfill(app: AppHandle) {
+    if LABEL_BACKFILL_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let _ = backfill_location_labels(&app).await;
+        LABEL_BACKFILL_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+async fn backfill_location_labels(app: &AppHandle) -> AppResult<()> {
+    use crate::coordinates::is_coordinate_only;
+
+    if let Some(location) = crate::commands::get_solar_settings()?.location {
+        if is_coordinate_only(&location) {
+            if let Ok(candidate) =
+                crate::commands::reverse_geocode_internal(location.latitude, location.longitude)
+                    .await
+            {
+                // The user may have picked another place while the request was in flight.
+                let current = crate::commands::get_solar_settings()?.location;
+                if current.is_some_and(|current| is_same_unlabeled_point(&current, &location)) {
+                    let mut labeled = location;
+                    labeled.display_name = candidate.display_name;
+                    save_solar_location_for_app(app, &labeled)?;
+                    crate::commands::finish_solar_location_change(app)?;
+                }
+            }
+        }
+    }
+
+    let mut labeled_any = false;
+    for saved in read_saved_locations()?.locations {
+        if !is_coordinate_only(&saved.location) {
+            continue;
+        }
+
+        let Ok(candidate) = crate::commands::reverse_geocode_internal(
+            saved.location.latitude,
+            saved.location.longitude,
+        )
+        .await
+        else {
+            continue;
+        };
+
+        // Renamed, moved or deleted while the request was in flight: leave it alone.
+        crate::settings::update(|settings| {
+            if let Ok(current) = saved_location_mut(settings, &saved.id) {
+                if is_same_unlabeled_point(&current.location, &saved.location) {
+                    current.location.display_name = candidate.display_name;
+                    labeled_any = true;
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    if labeled_any {
+        emit_saved_locations_changed(app)?;
+    }
+
+    Ok(())
+}
+
+/// Re-fetches place names of the solar location and saved locations in the current UI
+/// language. Reruns until the language stops changing underneath it.
+pub fn spawn_location_relocalization(app: AppHandle) {
+    if RELOCALIZE_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let language = crate::i18n::current_shared_locale();
+            let _ = relocalize_location_labels(&app).await;
+            if crate::i18n::current_shared_locale() == language {
+                break;
+            }
+        }
+        RELOCALIZE_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+async fn relocalize_location_labels(app: &AppHandle) -> AppResult<()> {
+    if let Some(location) = crate::commands::get_solar_settings()?.location {
+        if let Some(display_name) = localized_display_name(&location).await {
+            let current = crate::commands::get_solar_settings()?.location;
+            if current.is_some_and(|current| is_same_location(&current, &location)) {
+                let mut localized = location;
+                localized.display_name = display_name;
+                save_solar_location_for_app(app, &localized)?;
+                crate::commands::finish_solar_location_change(app)?;
+            }
+        }
+    }
+
+    let mut localized_any = false;
+    for saved in read_saved_locations()?.locations {
+        let Some(display_name) = localized_display_name(&saved.location).await else {
+            continue;
+        };
+
+        crate::settings::update(|settings| {
+            if let Ok(current) = saved_location_mut(settings, &saved.id) {
+                if is_same_location(&current.location, &saved.location) {
+                    current.location.display_name = display_name;
+                    localized_any = true;
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    if localized_any {
+        emit_saved_locations_changed(app)?;
+    }
+
+    Ok(())
+}
+
+/// Geocoded locations are looked up again by their address; reverse-labelled coordinates
+/// are reverse geocoded again. Bare coordinates and offline city names have nothing to
+/// translate. `None` when the label would not change.
+async fn localized_display_name(location: &GeocodeResult) -> Option<String> {
+    let display_name = match location.source {
+        LocationSource::Geocoded => {
+            let result = crate::geocoder::geocode(&location.address).await.ok()?;
+            let drift_km = crate::offline_geocoder::distance_km(
+                location.latitude,
+                location.longitude,
+                result.latitude,
+                result.longitude,
+            );
+            (drift_km <= RELOCALIZE_MAX_DRIFT_KM).then_some(result.display_name)?
+        }
+        LocationSource::Manual | LocationSource::Device
+            if !crate::coordinates::is_coordinate_only(location) =>
+        {
+            let candidate =
+                crate::commands::reverse_geocode_internal(location.latitude, location.longitude)
+                    .await
+                    .ok()?;
+            (candidate.source != LocationSource::Offline).then_some(candidate.display_name)?
+        }
+        _ => return None,
+    };
+
+    (display_name != location.display_name).then_some(display_name)
+}
+
+fn is_same_location(current: &GeocodeResult, original: &GeocodeResult) -> bool {
+    current.latitude == original.latitude
+        && current.longitude == original.longitude
+        && current.display_name == original.display_name
+}
+
+fn is_same_unlabeled_point(current: &GeocodeResult, original: &GeocodeResult) -> bool {
+    crate::coordinates::is_coordinate_only(current)
+        && current.latitude == original.latitude
+        && current.longitude == original.longitude
+}
+
+fn emit_saved_locations_changed(app: &AppHandle) -> AppResult<SavedLocations> {
+    let saved_locations = read_saved_locations()?;
+    let _ = app.emit(SAVED_LOCATIONS_CHANGED_EVENT, &saved_locations);
+    Ok(saved_locations)
+}
+
+fn normalize_location_name(name: &str) -> AppResult<String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(err("errors.locations.name_empty"));
+    }
+
+    if trimmed.chars().count() > LOCATION_NAME_MAX_CHARS {
+        return Err(
+            err("errors.locations.name_too_long").with_param("max", LOCATION_NAME_MAX_CHARS)
+        );
+    }
+
+    Ok(trimmed.to_string())
+}
+
+fn normalize_network_rules(rules: Vec<NetworkRule>) -> AppResult<Vec<NetworkRule>> {
+    let mut normalized: Vec<NetworkRule> = Vec::new();
+    for rule in rules {
+        let value = match rule.kind {
+            // SSIDs are case-sensitive and may carry meaningful spaces.
+            NetworkRuleKind::WifiSsid => rule.value,
+            NetworkRuleKind::DnsSuffix => rule
+                .value
+                .trim()
+                .trim_matches('.')
+                .to_ascii_lowercase(),
+        };
+        if value.trim().is_empty() {
+            return Err(err("errors.locations.network_rule_empty"));
+        }
+        if value.chars().count() > NETWORK_RULE_MAX_CHARS {
+            return Err(err("errors.locations.network_rule_too_long")
+                .with_param("max", NETWORK_RULE_MAX_CHARS));
+        }
+
+        let rule = NetworkRule {
+            kind: rule.kind,
+            value,
+        };
+        if !normalized.contains(&rule) {
+            normalized.push(rule);
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// Checks a full list of saved locations, e.g. from an import, against the same rules as
+/// the individual commands.
+pub(crate) fn validate_saved_locations(saved_locations: &SavedLocations) -> AppResult<()> {
+    let mut seen_ids: Vec<&str> = Vec::new();
+    for saved in &saved_locations.locations {
+        let id = saved.id.as_str();
+        if id.trim().is_empty() || id.contains('\\') || seen_ids.contains(&id) {
+            return Err(err("errors.locations.invalid_id").with_param("id", id));
+        }
+        seen_ids.push(id);
+
+        normalize_location_name(&saved.name)?;
+        normalize_network_rules(saved.network_rules.clone())?;
+        crate::coordinates::validate_coordinates(
+            saved.location.latitude,
+            saved.location.longitude,
+        )?;
+    }
+
+    if let Some(active_id) = saved_locations.active_id.as_deref() {
+        if !seen_ids.contains(&active_id) {
+            return Err(err("errors.locations.not_found").with_param("id", active_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn next_location_id(saved_locations: &SavedLocations) -> String {
+    let mut stamp = Utc::now().timestamp_millis();
+    loop {
+        let id = format!("loc-{stamp}");
+        if !saved_locations.locations.iter().any(|saved| saved.id == id) {
+            return id;
+        }
+        stamp += 1;
+    }
+}
+
+/// Looks `id` up in settings being changed, so the lookup and the write happen in the same
+/// `settings::update`.
+fn saved_location_mut<'a>(
+    settings: &'a mut AppSettings,
+    id: &str,
+) -> AppResult<&'a mut SavedLocation> {
+    settings
+        .saved_locations
+        .locations
+        .iter_mut()
+        .find(|saved| saved.id == id)
+        .ok_or_else(|| err("errors.locations.not_found").with_param("id", id))
+}
+
+fn read_saved_locations() -> AppResult<SavedLocations> {
+    Ok(crate::settings::load()?.saved_locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(display_name: &str, latitude: f64, source: LocationSource) -> GeocodeResult {
+        GeocodeResult {
+            address: display_name.to_string(),
+            display_name: display_name.to_string(),
+            latitude,
+            longitude: 13.4,
+            source,
+        }
+    }
+
+    fn settings_with_active_office() -> AppSettings {
+        let office = location("Berlin office", 52.5, LocationSource::Geocoded);
+        let mut settings = AppSettings::default();
+        settings.solar.location = Some(office.clone());
+        settings.saved_locations = SavedLocations {
+            locations: vec![SavedLocation {
+                id: "loc-1".to_string(),
+                name: "Office".to_string(),
+                location: office,
+                network_rules: Vec::new(),
+            }],
+            active_id: Some("loc-1".to_string()),
+        };
+        settings
+    }
+
+    #[test]
+    fn detected_location_leaves_saved_locations_alone() {
+        let mut settings = settings_with_active_office();
+        let saved_before = settings.saved_locations.locations.clone();
+        let device = location("48.1, 13.4", 48.1, LocationSource::Device);
+
+        let changed = set_solar_location(&mut settings, &device);
+
+        assert!(changed);
+        assert_eq!(settings.solar.location, Some(device));
+        assert_eq!(settings.saved_locations.locations, saved_before);
+        assert_eq!(settings.saved_locations.active_id, None);
+    }
+
+    #[test]
+    fn approximate_ip_location_leaves_saved_locations_alone() {
+        let mut settings = settings_with_active_office();
+        let saved_before = settings.saved_locations.locations.clone();
+        let approximate = location("Hamburg, DE", 53.6, LocationSource::IpApproximate);
+
+        set_solar_location(&mut settings, &approximate);
+
+        assert_eq!(settings.solar.location, Some(approximate));
+        assert_eq!(settings.saved_locations.locations, saved_before);
+        assert_eq!(settings.saved_locations.active_id, None);
+    }
+
+    #[test]
+    fn chosen_location_updates_the_active_saved_location() {
+        let mut settings = settings_with_active_office();
+        let chosen = location("Potsdam", 52.4, LocationSource::Geocoded);
+
+        let changed = set_solar_location(&mut settings, &chosen);
+
+        assert!(changed);
+        assert_eq!(settings.saved_locations.locations[0].location, chosen);
+        assert_eq!(settings.saved_locations.active_id.as_deref(), Some("loc-1"));
+    }
+}
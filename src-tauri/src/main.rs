@@ -1,14 +1,48 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod app_log;
+mod app_settings;
 mod commands;
+mod coordinates;
+mod geocode_cache;
+mod geocoder;
+mod http;
 mod i18n;
+mod location_provider;
+mod locations;
 mod main_window;
 mod models;
+mod network;
+mod offline_geocoder;
+mod paths;
+mod proxy;
+mod reset;
+mod secret;
+mod settings;
+mod settings_schema;
+mod settings_transfer;
+mod simulation;
+mod theme_backend;
+mod timezone;
 mod tray;
 
 use tauri::Manager;
 
 fn main() {
+    if let Some(settings_file) = paths::portable_settings_file() {
+        settings::install_store(Box::new(settings::JsonFileSettingsStore::new(settings_file)));
+    }
+    if std::env::args().any(|arg| arg == reset::CLEANUP_ARG) {
+        let restore_theme = std::env::args().any(|arg| arg == reset::RESTORE_THEME_ARG);
+        let keep_data = std::env::args().any(|arg| arg == reset::KEEP_DATA_ARG);
+        let exit_code = match reset::run_cleanup(restore_theme, keep_data) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        };
+        std::process::exit(exit_code);
+    }
+    settings_schema::run_startup_migrations();
+
     tauri::Builder::default()
         .setup(|app| {
             let lite_launch = std::env::args().any(|arg| arg == "--lite");
@@ -16,25 +50,65 @@ fn main() {
             tray::setup_tray(&app.handle(), !lite_launch)?;
             tray::refresh_tray_language()?;
             commands::start_auto_theme_worker(app.handle().clone());
+            location_provider::start_location_auto_update_worker(app.handle().clone());
+            timezone::start_time_zone_watcher(app.handle().clone());
+            network::start_network_location_worker(app.handle().clone());
             let _ = commands::apply_auto_theme_for_app(&app.handle());
+            locations::spawn_location_label_backfill(app.handle().clone());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            app_settings::get_app_settings,
+            app_settings::update_app_settings,
             commands::get_theme_state,
             commands::set_theme_state,
             commands::get_language_settings,
             commands::set_language_preference,
             commands::geocode_address,
+            commands::search_locations,
+            commands::reverse_geocode,
             commands::get_sun_times_by_address,
             commands::get_sun_times_by_saved_location,
+            commands::get_sun_times_by_coordinates,
             commands::get_solar_settings,
             commands::save_solar_location,
+            commands::save_solar_coordinates,
             commands::set_auto_theme_enabled,
             commands::set_sunset_offset_minutes,
+            commands::set_dry_run_enabled,
+            commands::set_prefer_offline_geocoding,
+            geocoder::get_geocoder_settings,
+            geocoder::set_geocoder_settings,
+            geocode_cache::clear_geocode_cache,
+            proxy::get_proxy_settings,
+            proxy::set_proxy_settings,
+            proxy::test_network_connection,
+            reset::reset_settings,
+            location_provider::get_ip_location_settings,
+            location_provider::set_ip_location_settings,
+            location_provider::detect_approximate_location,
+            location_provider::get_location_auto_update_settings,
+            location_provider::set_location_auto_update_settings,
+            timezone::get_time_zone_change,
+            timezone::dismiss_time_zone_change,
+            network::get_network_location_status,
+            settings_schema::get_settings_report,
+            settings_transfer::export_settings,
+            settings_transfer::preview_settings_import,
+            settings_transfer::import_settings,
             commands::get_startup_state,
             commands::set_startup_enabled,
             commands::open_external_url,
+            commands::get_auto_theme_history,
+            locations::get_saved_locations,
+            locations::add_saved_location,
+            locations::rename_saved_location,
+            locations::set_saved_location_network_rules,
+            locations::delete_saved_location,
+            locations::activate_saved_location,
+            simulation::start_auto_theme_simulation,
+            simulation::stop_auto_theme_simulation,
         ])
         .on_window_event(|window, event| {
             if window.label() != main_window::MAIN_WINDOW_LABEL {
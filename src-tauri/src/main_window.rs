@@ -17,23 +17,26 @@ fn create_main_window(app: &AppHandle) -> tauri::Result<tauri::WebviewWindow> {
         return Ok(window);
     }
 
-    if let Some(config) = app
+    let config = app
         .config()
         .app
         .windows
         .iter()
         .find(|window| window.label == MAIN_WINDOW_LABEL)
-    {
-        return tauri::WebviewWindowBuilder::from_config(app, config)?.build();
-    }
+        .or_else(|| app.config().app.windows.first());
 
-    if let Some(config) = app.config().app.windows.first() {
-        return tauri::WebviewWindowBuilder::from_config(app, config)?.build();
-    }
+    let builder = match config {
+        Some(config) => tauri::WebviewWindowBuilder::from_config(app, config)?,
+        None => {
+            tauri::WebviewWindowBuilder::new(app, MAIN_WINDOW_LABEL, tauri::WebviewUrl::default())
+                .title("WinLux")
+        }
+    };
 
-    tauri::WebviewWindowBuilder::new(app, MAIN_WINDOW_LABEL, tauri::WebviewUrl::default())
-        .title("WinLux")
-        .build()
+    match crate::paths::portable_webview_data_dir() {
+        Some(data_dir) => builder.data_directory(data_dir).build(),
+        None => builder.build(),
+    }
 }
 
 pub fn open_main_window(app: &AppHandle) {
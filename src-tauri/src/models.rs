@@ -31,6 +31,23 @@ pub enum ThemeMode {
     Dark,
 }
 
+impl ThemeMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThemeMode::Light => "light",
+            ThemeMode::Dark => "dark",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        match value.trim() {
+            "light" => Some(ThemeMode::Light),
+            "dark" => Some(ThemeMode::Dark),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ThemeState {
     pub apps: ThemeMode,
@@ -44,12 +61,169 @@ pub struct LanguageSettings {
     pub available: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationSource {
+    #[default]
+    Geocoded,
+    Manual,
+    Offline,
+    /// Derived from the public IP address; only accurate to roughly the city or region.
+    IpApproximate,
+    /// Estimated from the system time zone; never stored.
+    TimeZone,
+    /// Reported by the operating system's location service.
+    Device,
+}
+
+impl LocationSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LocationSource::Geocoded => "geocoded",
+            LocationSource::Manual => "manual",
+            LocationSource::Offline => "offline",
+            LocationSource::IpApproximate => "ip_approximate",
+            LocationSource::TimeZone => "time_zone",
+            LocationSource::Device => "device",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Self {
+        match value.trim() {
+            "manual" => LocationSource::Manual,
+            "offline" => LocationSource::Offline,
+            "ip_approximate" => LocationSource::IpApproximate,
+            "time_zone" => LocationSource::TimeZone,
+            "device" => LocationSource::Device,
+            _ => LocationSource::Geocoded,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GeocoderProvider {
+    #[default]
+    Nominatim,
+    NominatimSelfHosted,
+    Photon,
+}
+
+impl GeocoderProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GeocoderProvider::Nominatim => "nominatim",
+            GeocoderProvider::NominatimSelfHosted => "nominatim_self_hosted",
+            GeocoderProvider::Photon => "photon",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Self {
+        match value.trim() {
+            "nominatim_self_hosted" => GeocoderProvider::NominatimSelfHosted,
+            "photon" => GeocoderProvider::Photon,
+            _ => GeocoderProvider::Nominatim,
+        }
+    }
+}
+
+/// `endpoint` is the user-configured base URL; empty means `default_endpoint`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeocoderSettings {
+    pub provider: GeocoderProvider,
+    pub endpoint: String,
+    pub default_endpoint: String,
+}
+
+/// `endpoint` is the user-configured lookup URL; empty means `default_endpoint`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpLocationSettings {
+    pub enabled: bool,
+    pub endpoint: String,
+    pub default_endpoint: String,
+}
+
+/// The system time zone no longer matches the one recorded with the solar location.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeZoneChange {
+    /// Windows time zone IDs, e.g. `W. Europe Standard Time`.
+    pub previous_time_zone: String,
+    pub current_time_zone: String,
+    pub current_iana_time_zone: Option<String>,
+    /// Saved locations that lie in the current time zone.
+    pub matching_location_ids: Vec<String>,
+    /// What the auto theme uses until the user responds.
+    pub provisional_location: Option<GeocodeResult>,
+}
+
+/// Periodically replaces the solar location with the device position once it has moved
+/// at least `min_distance_km`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LocationAutoUpdateSettings {
+    pub enabled: bool,
+    pub interval_hours: u32,
+    pub min_distance_km: u32,
+    /// RFC 3339 time of the last position check; `None` before the first one.
+    pub last_checked_at: Option<String>,
+}
+
+/// Where outbound requests get their proxy from, in the order they are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxySource {
+    Manual,
+    Environment,
+    System,
+    Direct,
+}
+
+/// The manually configured proxy; an empty `url` defers to the environment and system
+/// proxy. The password is write-only and never sent back to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxySettings {
+    pub url: String,
+    pub username: String,
+    pub has_password: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTestResult {
+    pub proxy_source: ProxySource,
+    /// Proxy URL with credentials removed; `None` for direct connections.
+    pub proxy_url: Option<String>,
+    pub endpoint: String,
+    pub status: u16,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GeocodeResult {
     pub address: String,
     pub display_name: String,
     pub latitude: f64,
     pub longitude: f64,
+    #[serde(default)]
+    pub source: LocationSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeocodeBoundingBox {
+    pub south: f64,
+    pub north: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationCandidate {
+    pub display_name: String,
+    pub place_type: String,
+    pub country_code: Option<String>,
+    pub bounding_box: Option<GeocodeBoundingBox>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub source: LocationSource,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,12 +251,344 @@ pub struct SunTimesResult {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolarSettings {
+    /// The saved location.
     pub location: Option<GeocodeResult>,
+    /// Estimate from the system time zone that the auto theme follows instead of `location`,
+    /// because none has been saved or the system time zone changed since it was.
+    pub provisional_location: Option<GeocodeResult>,
     pub auto_theme_enabled: bool,
     pub sunset_offset_minutes: i64,
+    pub dry_run_enabled: bool,
+    pub prefer_offline_geocoding: bool,
+}
+
+impl SolarSettings {
+    /// The location the auto theme schedules by.
+    pub fn effective_location(&self) -> Option<&GeocodeResult> {
+        self.provisional_location
+            .as_ref()
+            .or(self.location.as_ref())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StartupState {
     pub enabled: bool,
+    /// Portable copies never register themselves in the Run key.
+    pub portable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoThemeHistoryEntry {
+    pub at_local: String,
+    pub at_unix: i64,
+    pub previous: ThemeMode,
+    pub theme: ThemeMode,
+    pub simulated: bool,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoThemeSimulationStatus {
+    pub run_id: u64,
+    pub running: bool,
+    pub days: u32,
+    pub speed: u32,
+    pub simulated_start: String,
+    pub simulated_end: String,
+    pub simulated_now: String,
+    pub theme: ThemeMode,
+    pub switch_count: u32,
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeDryRunChange {
+    pub current: ThemeState,
+    pub intended: ThemeState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedLocation {
+    pub id: String,
+    pub name: String,
+    pub location: GeocodeResult,
+    /// Networks on which this location is activated automatically.
+    #[serde(default)]
+    pub network_rules: Vec<NetworkRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SavedLocations {
+    pub locations: Vec<SavedLocation>,
+    pub active_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkRuleKind {
+    WifiSsid,
+    DnsSuffix,
+}
+
+impl NetworkRuleKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkRuleKind::WifiSsid => "ssid",
+            NetworkRuleKind::DnsSuffix => "dns",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        match value.trim() {
+            "ssid" => Some(NetworkRuleKind::WifiSsid),
+            "dns" => Some(NetworkRuleKind::DnsSuffix),
+            _ => None,
+        }
+    }
+}
+
+/// A Wi-Fi SSID (exact match) or DNS suffix (matches the suffix and its subdomains).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetworkRule {
+    pub kind: NetworkRuleKind,
+    pub value: String,
+}
+
+/// What the machine is currently connected to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetworkIdentifiers {
+    pub wifi_ssids: Vec<String>,
+    pub dns_suffixes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkLocationReason {
+    /// No saved location has network rules.
+    NoRules,
+    NoMatch,
+    /// A single location matched and was activated.
+    Matched,
+    AlreadyActive,
+    /// Several locations matched equally well, so the active one was left alone.
+    Ambiguous,
+    NetworkUnavailable,
+    ActivationFailed,
+}
+
+/// The network location worker's last decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkLocationStatus {
+    pub identifiers: NetworkIdentifiers,
+    pub reason: NetworkLocationReason,
+    /// The matched location, or every candidate when the match was ambiguous.
+    pub location_ids: Vec<String>,
+    pub matched_rule: Option<NetworkRule>,
+    pub activated: bool,
+    pub checked_at: String,
+    pub error: Option<AppError>,
+}
+
+/// Every value WinLux persists, as held by the active `SettingsStore`. Live system state
+/// (current theme, Run key) is not part of it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Layout the values were written in; see `settings_schema` for the migrations.
+    #[serde(default = "crate::settings_schema::legacy_schema_version")]
+    pub schema_version: u32,
+    pub solar: StoredSolarSettings,
+    /// `auto` or a language name from the bundled list.
+    pub language_preference: String,
+    pub geocoder: StoredGeocoderSettings,
+    pub ip_location: StoredIpLocationSettings,
+    pub location_auto_update: LocationAutoUpdateSettings,
+    pub proxy: StoredProxySettings,
+    pub saved_locations: SavedLocations,
+    /// Theme in use before WinLux first changed it, restored by `--cleanup --restore-theme`.
+    pub original_theme: Option<ThemeState>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StoredSolarSettings {
+    pub location: Option<GeocodeResult>,
+    /// Windows time zone the location was saved or confirmed in; empty when unknown.
+    pub time_zone: String,
+    pub auto_theme_enabled: bool,
+    pub sunset_offset_minutes: i64,
+    pub dry_run_enabled: bool,
+    pub prefer_offline_geocoding: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct StoredGeocoderSettings {
+    pub provider: GeocoderProvider,
+    pub endpoint: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct StoredIpLocationSettings {
+    pub enabled: bool,
+    pub endpoint: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct StoredProxySettings {
+    pub url: String,
+    pub username: String,
+    /// Plain text in memory only; the settings stores keep it encrypted for the user.
+    pub password: String,
+}
+
+/// A stored value that couldn't be used and was replaced by its default for this session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsIssue {
+    /// Registry value or settings field, e.g. `SolarLatitude` or `solar.sunset_offset_minutes`.
+    pub field: String,
+    pub error: AppError,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedSettingsMigration {
+    pub version: u32,
+    pub name: String,
+    /// `false` when the settings already had the migrated shape.
+    pub changed: bool,
+}
+
+/// Outcome of the startup settings check.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettingsReport {
+    pub schema_version: u32,
+    pub migrations: Vec<AppliedSettingsMigration>,
+    pub issues: Vec<SettingsIssue>,
+    /// Set when the settings couldn't be loaded or saved at all.
+    pub error: Option<AppError>,
+}
+
+/// File written by `export_settings`. `version` is raised whenever the layout changes in a
+/// way older builds can't read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsDocument {
+    pub version: u32,
+    pub exported_at: String,
+    pub settings: AppSettings,
+    /// Run-at-startup preference; `None` when exported from a portable copy.
+    #[serde(default)]
+    pub startup_enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsFieldChange {
+    /// Dotted path such as `solar.sunset_offset_minutes`.
+    pub field: String,
+    pub current: serde_json::Value,
+    pub imported: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsImportPreview {
+    pub version: u32,
+    pub exported_at: String,
+    pub changes: Vec<SettingsFieldChange>,
+}
+
+/// Everything the settings page shows, as returned by `get_app_settings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettingsSnapshot {
+    pub solar: SolarSettings,
+    pub language: LanguageSettings,
+    pub startup: StartupState,
+    pub geocoder: GeocoderSettings,
+    pub ip_location: IpLocationSettings,
+    pub location_auto_update: LocationAutoUpdateSettings,
+    pub proxy: ProxySettings,
+    pub saved_locations: SavedLocations,
+}
+
+/// Partial update for `update_app_settings`. Sections and fields left out keep their
+/// current value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettingsPatch {
+    pub solar: Option<SolarSettingsPatch>,
+    pub language_preference: Option<String>,
+    pub startup_enabled: Option<bool>,
+    pub geocoder: Option<GeocoderSettingsPatch>,
+    pub ip_location: Option<IpLocationSettingsPatch>,
+    pub location_auto_update: Option<LocationAutoUpdateSettingsPatch>,
+    pub proxy: Option<ProxySettingsPatch>,
+}
+
+/// The solar location is changed through its own commands, which geocode it first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolarSettingsPatch {
+    pub auto_theme_enabled: Option<bool>,
+    pub sunset_offset_minutes: Option<i64>,
+    pub dry_run_enabled: Option<bool>,
+    pub prefer_offline_geocoding: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeocoderSettingsPatch {
+    pub provider: Option<GeocoderProvider>,
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpLocationSettingsPatch {
+    pub enabled: Option<bool>,
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocationAutoUpdateSettingsPatch {
+    pub enabled: Option<bool>,
+    pub interval_hours: Option<u32>,
+    pub min_distance_km: Option<u32>,
+}
+
+/// An empty `password` clears it; leaving it out keeps the stored one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettingsPatch {
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsChange {
+    /// Dotted path into `AppSettingsSnapshot`, e.g. `solar.sunset_offset_minutes`.
+    pub field: String,
+    pub previous: serde_json::Value,
+    pub current: serde_json::Value,
+}
+
+/// Payload of `SETTINGS_CHANGED_EVENT`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsChanged {
+    pub changes: Vec<SettingsChange>,
+    pub settings: AppSettingsSnapshot,
+}
+
+/// What `reset_settings` puts back to its defaults.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetScope {
+    /// Every persisted setting and the Run-at-startup entry.
+    All,
+    /// Solar location, auto theme, sunset offset, dry run and offline geocoding.
+    Solar,
+    Language,
+    Startup,
 }
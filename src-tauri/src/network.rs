@@ -0,0 +1,641 @@
+use crate::models::{
+    AppError, AppResult, NetworkIdentifiers, NetworkLocationReason, NetworkLocationStatus,
+    NetworkRule, NetworkRuleKind, SavedLocations,
+};
+use chrono::Utc;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use tokio::time::timeout;
+
+pub const NETWORK_LOCATION_STATUS_CHANGED_EVENT: &str = "network-location-status-changed";
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// `;`-separated rules in the registry format, e.g. `ssid:Office;dns:corp.example.com`,
+/// that replace the real network for exercising profile switching.
+const MOCK_NETWORK_ENV: &str = "WINLUX_MOCK_NETWORK";
+
+static NETWORK_LOCATION_WORKER_STARTED: OnceLock<()> = OnceLock::new();
+static NETWORK_LOCATION_WAKE_SIGNAL: OnceLock<Notify> = OnceLock::new();
+static NETWORK_LOCATION_STATUS: Mutex<Option<NetworkLocationStatus>> = Mutex::new(None);
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Reports which networks the machine is connected to.
+pub trait NetworkEnvironment: Send + Sync {
+    fn identifiers(&self) -> AppResult<NetworkIdentifiers>;
+}
+
+/// Connected Wi-Fi SSIDs from the WLAN service and connection-specific DNS suffixes of
+/// adapters that are up.
+pub struct WindowsNetworkEnvironment;
+
+impl NetworkEnvironment for WindowsNetworkEnvironment {
+    fn identifiers(&self) -> AppResult<NetworkIdentifiers> {
+        Ok(NetworkIdentifiers {
+            wifi_ssids: read_wifi_ssids(),
+            dns_suffixes: read_dns_suffixes()?,
+        })
+    }
+}
+
+/// Reports fixed identifiers.
+pub struct MockNetworkEnvironment {
+    identifiers: NetworkIdentifiers,
+}
+
+impl MockNetworkEnvironment {
+    pub fn new(identifiers: NetworkIdentifiers) -> Self {
+        Self { identifiers }
+    }
+
+    fn from_env() -> Option<Self> {
+        let value = std::env::var(MOCK_NETWORK_ENV).ok()?;
+        let mut identifiers = NetworkIdentifiers::default();
+        for rule in value.split(';').filter_map(parse_network_rule) {
+            match rule.kind {
+                NetworkRuleKind::WifiSsid => identifiers.wifi_ssids.push(rule.value),
+                NetworkRuleKind::DnsSuffix => identifiers.dns_suffixes.push(rule.value),
+            }
+        }
+        Some(Self::new(identifiers))
+    }
+}
+
+impl NetworkEnvironment for MockNetworkEnvironment {
+    fn identifiers(&self) -> AppResult<NetworkIdentifiers> {
+        Ok(self.identifiers.clone())
+    }
+}
+
+pub fn network_environment() -> Box<dyn NetworkEnvironment> {
+    match MockNetworkEnvironment::from_env() {
+        Some(mock) => Box::new(mock),
+        None => Box::new(WindowsNetworkEnvironment),
+    }
+}
+
+pub fn parse_network_rule(raw: &str) -> Option<NetworkRule> {
+    let (kind, value) = raw.split_once(':')?;
+    let kind = NetworkRuleKind::from_value(kind)?;
+    (!value.trim().is_empty()).then(|| NetworkRule {
+        kind,
+        value: value.to_string(),
+    })
+}
+
+pub fn format_network_rule(rule: &NetworkRule) -> String {
+    format!("{}:{}", rule.kind.as_str(), rule.value)
+}
+
+/// Activates the saved location tied to the current network whenever the network changes.
+/// Between changes the user's own choice of location is left alone.
+pub fn start_network_location_worker(app: AppHandle) {
+    if NETWORK_LOCATION_WORKER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    let wake_signal = NETWORK_LOCATION_WAKE_SIGNAL.get_or_init(Notify::new);
+
+    tauri::async_runtime::spawn(async move {
+        let environment = network_environment();
+        let mut last_identifiers: Option<NetworkIdentifiers> = None;
+        let mut reevaluate = true;
+
+        loop {
+            let identifiers = environment.identifiers();
+            let changed = match &identifiers {
+                Ok(identifiers) => last_identifiers.as_ref() != Some(identifiers),
+                Err(_) => last_identifiers.is_some(),
+            };
+
+            if reevaluate || changed {
+                last_identifiers = identifiers.as_ref().ok().cloned();
+                let status = apply_network_location(&app, identifiers);
+                if let Ok(mut current) = NETWORK_LOCATION_STATUS.lock() {
+                    *current = Some(status.clone());
+                }
+                let _ = app.emit(NETWORK_LOCATION_STATUS_CHANGED_EVENT, &status);
+            }
+
+            reevaluate = timeout(NETWORK_POLL_INTERVAL, wake_signal.notified())
+                .await
+                .is_ok();
+        }
+    });
+}
+
+/// Makes the worker decide again on its next pass, e.g. after network rules changed.
+pub fn reevaluate_network_location() {
+    if let Some(signal) = NETWORK_LOCATION_WAKE_SIGNAL.get() {
+        signal.notify_one();
+    }
+}
+
+/// `None` until the worker has looked at the network once.
+#[tauri::command]
+pub fn get_network_location_status() -> AppResult<Option<NetworkLocationStatus>> {
+    Ok(NETWORK_LOCATION_STATUS
+        .lock()
+        .ok()
+        .and_then(|status| status.clone()))
+}
+
+fn apply_network_location(
+    app: &AppHandle,
+    identifiers: AppResult<NetworkIdentifiers>,
+) -> NetworkLocationStatus {
+    let mut status = network_location_status(identifiers, crate::locations::get_saved_locations);
+
+    if status.reason == NetworkLocationReason::Matched {
+        match crate::locations::activate_saved_location_for_app(app, &status.location_ids[0]) {
+            Ok(_) => status.activated = true,
+            Err(error) => {
+                status.reason = NetworkLocationReason::ActivationFailed;
+                status.error = Some(error);
+            }
+        }
+    }
+
+    status
+}
+
+/// The decision for the network in `identifiers`, before any location is activated.
+/// Saved locations are only loaded once the network is known.
+fn network_location_status(
+    identifiers: AppResult<NetworkIdentifiers>,
+    saved_locations: impl FnOnce() -> AppResult<SavedLocations>,
+) -> NetworkLocationStatus {
+    let mut status = NetworkLocationStatus {
+        identifiers: NetworkIdentifiers::default(),
+        reason: NetworkLocationReason::NetworkUnavailable,
+        location_ids: Vec::new(),
+        matched_rule: None,
+        activated: false,
+        checked_at: Utc::now().to_rfc3339(),
+        error: None,
+    };
+
+    let identifiers = match identifiers {
+        Ok(identifiers) => identifiers,
+        Err(error) => {
+            status.error = Some(error);
+            return status;
+        }
+    };
+
+    let saved_locations = match saved_locations() {
+        Ok(saved_locations) => saved_locations,
+        Err(error) => {
+            status.identifiers = identifiers;
+            status.reason = NetworkLocationReason::ActivationFailed;
+            status.error = Some(error);
+            return status;
+        }
+    };
+
+    let decision = decide_network_location(&saved_locations, &identifiers);
+    status.identifiers = identifiers;
+    status.reason = decision.reason;
+    status.location_ids = decision.location_ids;
+    status.matched_rule = decision.matched_rule;
+    status
+}
+
+pub struct NetworkLocationDecision {
+    pub reason: NetworkLocationReason,
+    pub location_ids: Vec<String>,
+    pub matched_rule: Option<NetworkRule>,
+}
+
+/// Picks the saved location for the given network. An SSID match beats a DNS suffix
+/// match, since several Wi-Fi networks often share one corporate domain.
+pub fn decide_network_location(
+    saved_locations: &SavedLocations,
+    identifiers: &NetworkIdentifiers,
+) -> NetworkLocationDecision {
+    let mut decision = NetworkLocationDecision {
+        reason: NetworkLocationReason::NoRules,
+        location_ids: Vec::new(),
+        matched_rule: None,
+    };
+
+    if saved_locations
+        .locations
+        .iter()
+        .all(|saved| saved.network_rules.is_empty())
+    {
+        return decision;
+    }
+
+    for kind in [NetworkRuleKind::WifiSsid, NetworkRuleKind::DnsSuffix] {
+        let matches: Vec<(&str, &NetworkRule)> = saved_locations
+            .locations
+            .iter()
+            .filter_map(|saved| {
+                saved
+                    .network_rules
+                    .iter()
+                    .filter(|rule| rule.kind == kind)
+                    .find(|rule| rule_matches(rule, identifiers))
+                    .map(|rule| (saved.id.as_str(), rule))
+            })
+            .collect();
+
+        let Some(&(first_id, first_rule)) = matches.first() else {
+            continue;
+        };
+
+        decision.matched_rule = Some(first_rule.clone());
+        if let Some(&(active_id, active_rule)) = matches
+            .iter()
+            .find(|(id, _)| saved_locations.active_id.as_deref() == Some(*id))
+        {
+            decision.reason = NetworkLocationReason::AlreadyActive;
+            decision.location_ids = vec![active_id.to_string()];
+            decision.matched_rule = Some(active_rule.clone());
+        } else if matches.len() == 1 {
+            decision.reason = NetworkLocationReason::Matched;
+            decision.location_ids = vec![first_id.to_string()];
+        } else {
+            decision.reason = NetworkLocationReason::Ambiguous;
+            decision.location_ids = matches.iter().map(|(id, _)| id.to_string()).collect();
+            decision.matched_rule = None;
+        }
+        return decision;
+    }
+
+    decision.reason = NetworkLocationReason::NoMatch;
+    decision
+}
+
+fn rule_matches(rule: &NetworkRule, identifiers: &NetworkIdentifiers) -> bool {
+    match rule.kind {
+        NetworkRuleKind::WifiSsid => identifiers.wifi_ssids.contains(&rule.value),
+        NetworkRuleKind::DnsSuffix => {
+            let expected = rule.value.trim_matches('.').to_ascii_lowercase();
+            identifiers.dns_suffixes.iter().any(|suffix| {
+                let suffix = suffix.trim_matches('.').to_ascii_lowercase();
+                suffix == expected || suffix.ends_with(&format!(".{expected}"))
+            })
+        }
+    }
+}
+
+/// Machines without Wi-Fi or with the WLAN service stopped simply report no SSIDs.
+fn read_wifi_ssids() -> Vec<String> {
+    use std::ffi::c_void;
+    use std::ptr;
+    use windows_sys::Win32::NetworkManagement::WiFi::{
+        wlan_interface_state_connected, wlan_intf_opcode_current_connection, WlanCloseHandle,
+        WlanEnumInterfaces, WlanFreeMemory, WlanOpenHandle, WlanQueryInterface,
+        WLAN_CONNECTION_ATTRIBUTES, WLAN_INTERFACE_INFO_LIST,
+    };
+
+    let mut ssids = Vec::new();
+
+    unsafe {
+        let mut negotiated_version: u32 = 0;
+        let mut client = 0;
+        if WlanOpenHandle(2, ptr::null(), &mut negotiated_version, &mut client) != 0 {
+            return ssids;
+        }
+
+        let mut interfaces: *mut WLAN_INTERFACE_INFO_LIST = ptr::null_mut();
+        if WlanEnumInterfaces(client, ptr::null(), &mut interfaces) == 0 {
+            let infos = std::slice::from_raw_parts(
+                (*interfaces).InterfaceInfo.as_ptr(),
+                (*interfaces).dwNumberOfItems as usize,
+            );
+
+            for info in infos
+                .iter()
+                .filter(|info| info.isState == wlan_interface_state_connected)
+            {
+                let mut data_size: u32 = 0;
+                let mut data: *mut c_void = ptr::null_mut();
+                if WlanQueryInterface(
+                    client,
+                    &info.InterfaceGuid,
+                    wlan_intf_opcode_current_connection,
+                    ptr::null(),
+                    &mut data_size,
+                    &mut data,
+                    ptr::null_mut(),
+                ) != 0
+                {
+                    continue;
+                }
+
+                let attributes = &*(data as *const WLAN_CONNECTION_ATTRIBUTES);
+                let ssid = &attributes.wlanAssociationAttributes.dot11Ssid;
+                let length = (ssid.uSSIDLength as usize).min(ssid.ucSSID.len());
+                let ssid = String::from_utf8_lossy(&ssid.ucSSID[..length]).into_owned();
+                if !ssid.is_empty() {
+                    ssids.push(ssid);
+                }
+                WlanFreeMemory(data);
+            }
+
+            WlanFreeMemory(interfaces as *const c_void);
+        }
+
+        WlanCloseHandle(client, ptr::null());
+    }
+
+    ssids
+}
+
+fn read_dns_suffixes() -> AppResult<Vec<String>> {
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{ERROR_BUFFER_OVERFLOW, ERROR_SUCCESS};
+    use windows_sys::Win32::NetworkManagement::IpHelper::{
+        GetAdaptersAddresses, GAA_FLAG_SKIP_ANYCAST, GAA_FLAG_SKIP_DNS_SERVER,
+        GAA_FLAG_SKIP_MULTICAST, IP_ADAPTER_ADDRESSES_LH,
+    };
+    use windows_sys::Win32::NetworkManagement::Ndis::IfOperStatusUp;
+    use windows_sys::Win32::Networking::WinSock::AF_UNSPEC;
+
+    let flags = GAA_FLAG_SKIP_ANYCAST | GAA_FLAG_SKIP_MULTICAST | GAA_FLAG_SKIP_DNS_SERVER;
+    // Recommended starting size; the call reports the size it needs when this is too small.
+    let mut size: u32 = 15 * 1024;
+    let mut buffer: Vec<u64>;
+
+    loop {
+        buffer = vec![0; (size as usize).div_ceil(std::mem::size_of::<u64>())];
+        let result = unsafe {
+            GetAdaptersAddresses(
+                u32::from(AF_UNSPEC),
+                flags,
+                ptr::null(),
+                buffer.as_mut_ptr() as *mut IP_ADAPTER_ADDRESSES_LH,
+                &mut size,
+            )
+        };
+        match result {
+            ERROR_SUCCESS => break,
+            ERROR_BUFFER_OVERFLOW => continue,
+            code => {
+                return Err(err_with_source(
+                    "errors.network_environment.unavailable",
+                    std::io::Error::from_raw_os_error(code as i32),
+                ))
+            }
+        }
+    }
+
+    let mut suffixes: Vec<String> = Vec::new();
+    let mut adapter = buffer.as_ptr() as *const IP_ADAPTER_ADDRESSES_LH;
+    while let Some(current) = unsafe { adapter.as_ref() } {
+        if current.OperStatus == IfOperStatusUp && !current.DnsSuffix.is_null() {
+            let suffix = unsafe {
+                let length = (0..)
+                    .take_while(|&i| *current.DnsSuffix.add(i) != 0)
+                    .count();
+                String::from_utf16_lossy(std::slice::from_raw_parts(current.DnsSuffix, length))
+            };
+            let suffix = suffix.trim().to_string();
+            if !suffix.is_empty() && !suffixes.contains(&suffix) {
+                suffixes.push(suffix);
+            }
+        }
+        adapter = current.Next;
+    }
+
+    Ok(suffixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GeocodeResult, LocationSource, SavedLocation};
+
+    /// Stands in for the machine's network; `None` behaves like a failed WLAN or adapter
+    /// query.
+    struct FakeNetworkEnvironment {
+        identifiers: Option<NetworkIdentifiers>,
+    }
+
+    impl FakeNetworkEnvironment {
+        fn connected(wifi_ssids: &[&str], dns_suffixes: &[&str]) -> Self {
+            let to_strings =
+                |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+            Self {
+                identifiers: Some(NetworkIdentifiers {
+                    wifi_ssids: to_strings(wifi_ssids),
+                    dns_suffixes: to_strings(dns_suffixes),
+                }),
+            }
+        }
+
+        fn unavailable() -> Self {
+            Self { identifiers: None }
+        }
+    }
+
+    impl NetworkEnvironment for FakeNetworkEnvironment {
+        fn identifiers(&self) -> AppResult<NetworkIdentifiers> {
+            self.identifiers
+                .clone()
+                .ok_or_else(|| AppError::new("errors.network_environment.unavailable"))
+        }
+    }
+
+    fn saved(id: &str, rules: &[&str]) -> SavedLocation {
+        SavedLocation {
+            id: id.to_string(),
+            name: id.to_string(),
+            location: GeocodeResult {
+                address: id.to_string(),
+                display_name: id.to_string(),
+                latitude: 0.0,
+                longitude: 0.0,
+                source: LocationSource::Manual,
+            },
+            network_rules: rules
+                .iter()
+                .map(|rule| parse_network_rule(rule).expect("valid rule"))
+                .collect(),
+        }
+    }
+
+    fn locations(locations: Vec<SavedLocation>, active_id: Option<&str>) -> SavedLocations {
+        SavedLocations {
+            locations,
+            active_id: active_id.map(str::to_string),
+        }
+    }
+
+    fn status_on(
+        environment: &FakeNetworkEnvironment,
+        saved_locations: &SavedLocations,
+    ) -> NetworkLocationStatus {
+        network_location_status(environment.identifiers(), || Ok(saved_locations.clone()))
+    }
+
+    fn offices() -> SavedLocations {
+        locations(
+            vec![
+                saved("berlin", &["ssid:Office-BER", "dns:ber.corp.example.com"]),
+                saved("munich", &["ssid:Office-MUC", "dns:corp.example.com"]),
+                saved("home", &["ssid:HomeNet"]),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    fn reports_no_rules_when_no_location_has_any() {
+        let saved_locations = locations(vec![saved("home", &[])], None);
+        let status = status_on(
+            &FakeNetworkEnvironment::connected(&["HomeNet"], &[]),
+            &saved_locations,
+        );
+
+        assert_eq!(status.reason, NetworkLocationReason::NoRules);
+        assert!(status.location_ids.is_empty());
+    }
+
+    #[test]
+    fn matches_a_single_ssid() {
+        let status = status_on(
+            &FakeNetworkEnvironment::connected(&["HomeNet"], &[]),
+            &offices(),
+        );
+
+        assert_eq!(status.reason, NetworkLocationReason::Matched);
+        assert_eq!(status.location_ids, ["home"]);
+        assert_eq!(status.matched_rule, parse_network_rule("ssid:HomeNet"));
+        assert!(status.error.is_none());
+    }
+
+    #[test]
+    fn prefers_an_ssid_match_over_a_dns_suffix_match() {
+        let environment = FakeNetworkEnvironment::connected(&["Office-BER"], &["corp.example.com"]);
+        let status = status_on(&environment, &offices());
+
+        assert_eq!(status.reason, NetworkLocationReason::Matched);
+        assert_eq!(status.location_ids, ["berlin"]);
+    }
+
+    #[test]
+    fn matches_dns_suffixes_and_their_subdomains_ignoring_case() {
+        let cases = [
+            ("corp.example.com", Some("munich")),
+            ("Dev.Corp.Example.COM.", Some("munich")),
+            ("notcorp.example.com", None),
+            ("example.com", None),
+        ];
+        let saved_locations = locations(vec![saved("munich", &["dns:corp.example.com"])], None);
+
+        for (suffix, expected) in cases {
+            let environment = FakeNetworkEnvironment::connected(&[], &[suffix]);
+            let status = status_on(&environment, &saved_locations);
+            match expected {
+                Some(id) => {
+                    assert_eq!(status.reason, NetworkLocationReason::Matched, "{suffix}");
+                    assert_eq!(status.location_ids, [id], "{suffix}");
+                }
+                None => assert_eq!(status.reason, NetworkLocationReason::NoMatch, "{suffix}"),
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_an_already_active_match_alone() {
+        let mut saved_locations = offices();
+        saved_locations.active_id = Some("home".to_string());
+        let status = status_on(
+            &FakeNetworkEnvironment::connected(&["HomeNet"], &[]),
+            &saved_locations,
+        );
+
+        assert_eq!(status.reason, NetworkLocationReason::AlreadyActive);
+        assert_eq!(status.location_ids, ["home"]);
+    }
+
+    #[test]
+    fn prefers_the_active_location_among_several_matches() {
+        let saved_locations = locations(
+            vec![saved("a", &["ssid:Shared"]), saved("b", &["ssid:Shared"])],
+            Some("b"),
+        );
+        let status = status_on(
+            &FakeNetworkEnvironment::connected(&["Shared"], &[]),
+            &saved_locations,
+        );
+
+        assert_eq!(status.reason, NetworkLocationReason::AlreadyActive);
+        assert_eq!(status.location_ids, ["b"]);
+    }
+
+    #[test]
+    fn reports_ambiguous_matches_without_picking_one() {
+        let saved_locations = locations(
+            vec![saved("a", &["ssid:Shared"]), saved("b", &["ssid:Shared"])],
+            None,
+        );
+        let status = status_on(
+            &FakeNetworkEnvironment::connected(&["Shared"], &[]),
+            &saved_locations,
+        );
+
+        assert_eq!(status.reason, NetworkLocationReason::Ambiguous);
+        assert_eq!(status.location_ids, ["a", "b"]);
+        assert!(status.matched_rule.is_none());
+    }
+
+    #[test]
+    fn reports_no_match_on_an_unknown_network() {
+        let environment = FakeNetworkEnvironment::connected(&["CoffeeShop"], &["isp.example.net"]);
+        let status = status_on(&environment, &offices());
+
+        assert_eq!(status.reason, NetworkLocationReason::NoMatch);
+        assert!(status.location_ids.is_empty());
+        assert_eq!(status.identifiers.wifi_ssids, ["CoffeeShop"]);
+    }
+
+    #[test]
+    fn reports_an_unavailable_network_without_loading_locations() {
+        let status =
+            network_location_status(FakeNetworkEnvironment::unavailable().identifiers(), || {
+                panic!("saved locations loaded although the network is unknown")
+            });
+
+        assert_eq!(status.reason, NetworkLocationReason::NetworkUnavailable);
+        assert_eq!(status.identifiers, NetworkIdentifiers::default());
+        assert!(status.error.is_some());
+    }
+
+    #[test]
+    fn keeps_the_identifiers_when_saved_locations_fail_to_load() {
+        let environment = FakeNetworkEnvironment::connected(&["HomeNet"], &[]);
+        let status = network_location_status(environment.identifiers(), || {
+            Err(AppError::new("errors.settings.load_failed"))
+        });
+
+        assert_eq!(status.reason, NetworkLocationReason::ActivationFailed);
+        assert_eq!(status.identifiers.wifi_ssids, ["HomeNet"]);
+        assert!(status.error.is_some());
+    }
+
+    #[test]
+    fn parses_rules_in_the_registry_format() {
+        assert_eq!(
+            parse_network_rule("ssid:Office"),
+            Some(NetworkRule {
+                kind: NetworkRuleKind::WifiSsid,
+                value: "Office".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_network_rule("dns:corp.example.com").map(|rule| rule.kind),
+            Some(NetworkRuleKind::DnsSuffix)
+        );
+        assert_eq!(parse_network_rule("dns: "), None);
+        assert_eq!(parse_network_rule("mac:00-11-22"), None);
+        assert_eq!(parse_network_rule("Office"), None);
+    }
+}
@@ -0,0 +1,387 @@
+use crate::models::{GeocodeResult, LocationCandidate, LocationSource};
+use std::cmp::Reverse;
+use std::sync::OnceLock;
+
+/// Tab-separated GeoNames-style extract: name, comma-separated alternate names, ISO country
+/// code, population, latitude, longitude and IANA time zone.
+const CITIES_DATA: &str = include_str!("../data/cities.tsv");
+const CITY_FIELD_COUNT: usize = 7;
+const PREFIX_MATCH_MIN_LENGTH: usize = 3;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+static CITIES: OnceLock<Vec<OfflineCity>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct OfflineCity {
+    pub name: String,
+    pub country_code: String,
+    pub population: u64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// IANA time zone identifier, e.g. `Europe/Berlin`.
+    pub time_zone: String,
+    search_names: Vec<String>,
+}
+
+impl OfflineCity {
+    pub fn display_name(&self) -> String {
+        format!("{}, {}", self.name, self.country_code)
+    }
+
+    pub fn to_location(&self, address: &str) -> GeocodeResult {
+        GeocodeResult {
+            address: address.trim().to_string(),
+            display_name: self.display_name(),
+            latitude: self.latitude,
+            longitude: self.longitude,
+            source: LocationSource::Offline,
+        }
+    }
+
+    pub fn to_candidate(&self) -> LocationCandidate {
+        LocationCandidate {
+            display_name: self.display_name(),
+            place_type: "city".to_string(),
+            country_code: Some(self.country_code.clone()),
+            bounding_box: None,
+            latitude: self.latitude,
+            longitude: self.longitude,
+            source: LocationSource::Offline,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchQuality {
+    Fuzzy,
+    Prefix,
+    Exact,
+}
+
+pub fn cities() -> &'static [OfflineCity] {
+    CITIES.get_or_init(|| CITIES_DATA.lines().filter_map(parse_city_line).collect())
+}
+
+/// Looks up `query` in the bundled city list. Matching ignores case and diacritics, accepts
+/// alternate and native-script names, tolerates small typos and ranks ties by population.
+/// A trailing `, XX` narrows the results to that country when `XX` is a country code in the
+/// list and the query as a whole names no city.
+pub fn search(query: &str, limit: usize) -> Vec<&'static OfflineCity> {
+    let name_query = normalize_name(query.split(',').next().unwrap_or_default());
+    if name_query.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+
+    // "Springfield, IL" is in the US, not Israel; the filter only applies when nothing
+    // matches the whole text, and is dropped again when it rules out every city.
+    let whole_matches = ranked_matches(&normalize_name(query), None);
+    let matches = if !whole_matches.is_empty() {
+        whole_matches
+    } else {
+        match trailing_country_code(query) {
+            Some(country) => {
+                let filtered = ranked_matches(&name_query, Some(country));
+                if filtered.is_empty() {
+                    ranked_matches(&name_query, None)
+                } else {
+                    filtered
+                }
+            }
+            None => ranked_matches(&name_query, None),
+        }
+    };
+
+    matches.into_iter().take(limit).collect()
+}
+
+fn trailing_country_code(query: &str) -> Option<&'static str> {
+    let (_, last) = query.rsplit_once(',')?;
+    let code = last.trim();
+    if code.len() != 2 {
+        return None;
+    }
+
+    cities()
+        .iter()
+        .find(|city| city.country_code.eq_ignore_ascii_case(code))
+        .map(|city| city.country_code.as_str())
+}
+
+/// Cities matching `name_query`, best match first and larger cities first among equals.
+fn ranked_matches(name_query: &str, country: Option<&str>) -> Vec<&'static OfflineCity> {
+    let query_chars: Vec<char> = name_query.chars().collect();
+    let mut matches: Vec<(MatchQuality, &'static OfflineCity)> = cities()
+        .iter()
+        .filter(|city| match country {
+            Some(country) => city.country_code == country,
+            None => true,
+        })
+        .filter_map(|city| {
+            best_match(city, name_query, &query_chars).map(|quality| (quality, city))
+        })
+        .collect();
+
+    matches.sort_by_key(|(quality, city)| (Reverse(*quality), Reverse(city.population)));
+    matches.into_iter().map(|(_, city)| city).collect()
+}
+
+pub fn geocode(query: &str) -> Option<GeocodeResult> {
+    search(query, 1)
+        .into_iter()
+        .next()
+        .map(|city| city.to_location(query))
+}
+
+/// The bundled city closest to the point, if one lies within `max_distance_km`.
+pub fn nearest(
+    latitude: f64,
+    longitude: f64,
+    max_distance_km: f64,
+) -> Option<&'static OfflineCity> {
+    cities()
+        .iter()
+        .map(|city| {
+            (
+                distance_km(latitude, longitude, city.latitude, city.longitude),
+                city,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance_km)
+        .min_by(|(left, _), (right, _)| left.total_cmp(right))
+        .map(|(_, city)| city)
+}
+
+/// The most populous bundled city in the IANA time zone `time_zone`.
+pub fn largest_city_in_time_zone(time_zone: &str) -> Option<&'static OfflineCity> {
+    cities()
+        .iter()
+        .filter(|city| city.time_zone.eq_ignore_ascii_case(time_zone))
+        .max_by_key(|city| city.population)
+}
+
+/// Great-circle distance using the haversine formula.
+pub fn distance_km(
+    from_latitude: f64,
+    from_longitude: f64,
+    to_latitude: f64,
+    to_longitude: f64,
+) -> f64 {
+    let delta_latitude = (to_latitude - from_latitude).to_radians();
+    let delta_longitude = (to_longitude - from_longitude).to_radians();
+    let a = (delta_latitude / 2.0).sin().powi(2)
+        + from_latitude.to_radians().cos()
+            * to_latitude.to_radians().cos()
+            * (delta_longitude / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+fn best_match(city: &OfflineCity, name_query: &str, query_chars: &[char]) -> Option<MatchQuality> {
+    let max_distance = max_edit_distance(query_chars.len());
+
+    city.search_names
+        .iter()
+        .filter_map(|name| {
+            if name == name_query {
+                Some(MatchQuality::Exact)
+            } else if query_chars.len() >= PREFIX_MATCH_MIN_LENGTH && name.starts_with(name_query) {
+                Some(MatchQuality::Prefix)
+            } else if max_distance > 0 {
+                let name_chars: Vec<char> = name.chars().collect();
+                (name_chars.len().abs_diff(query_chars.len()) <= max_distance
+                    && edit_distance(query_chars, &name_chars) <= max_distance)
+                    .then_some(MatchQuality::Fuzzy)
+            } else {
+                None
+            }
+        })
+        .max()
+}
+
+fn max_edit_distance(query_length: usize) -> usize {
+    match query_length {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+fn edit_distance(left: &[char], right: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+    let mut current = vec![0; right.len() + 1];
+
+    for (i, left_char) in left.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != right_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[right.len()]
+}
+
+fn parse_city_line(line: &str) -> Option<OfflineCity> {
+    if line.trim().is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != CITY_FIELD_COUNT {
+        return None;
+    }
+
+    let name = fields[0].trim();
+    let mut search_names = vec![normalize_name(name)];
+    for alternate in fields[1].split(',') {
+        let normalized = normalize_name(alternate);
+        if !normalized.is_empty() && !search_names.contains(&normalized) {
+            search_names.push(normalized);
+        }
+    }
+
+    Some(OfflineCity {
+        name: name.to_string(),
+        country_code: fields[2].trim().to_ascii_uppercase(),
+        population: fields[3].trim().parse().ok()?,
+        latitude: fields[4].trim().parse().ok()?,
+        longitude: fields[5].trim().parse().ok()?,
+        time_zone: fields[6].trim().to_string(),
+        search_names,
+    })
+}
+
+/// Lowercases, strips Latin diacritics and collapses punctuation into single spaces so that
+/// "São Paulo", "sao-paulo" and "SAO PAULO" compare equal.
+fn normalize_name(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut pending_space = false;
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if ('\u{0300}'..='\u{036f}').contains(&c) {
+            continue;
+        }
+
+        if c.is_alphanumeric() {
+            if pending_space && !output.is_empty() {
+                output.push(' ');
+            }
+            pending_space = false;
+            push_folded_char(c, &mut output);
+        } else {
+            pending_space = true;
+        }
+    }
+
+    output
+}
+
+fn push_folded_char(c: char, output: &mut String) {
+    let folded = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' | 'ș' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' | 'ț' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'þ' => "th",
+        _ => {
+            output.push(c);
+            return;
+        }
+    };
+
+    output.push_str(folded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::search;
+
+    /// Each query against the bundled list, with the display name of the best match.
+    fn assert_cases(cases: &[(&str, Option<&str>)]) {
+        for &(query, expected) in cases {
+            let actual = search(query, 1).first().map(|city| city.display_name());
+            assert_eq!(actual.as_deref(), expected, "{query:?}");
+        }
+    }
+
+    #[test]
+    fn folds_case_and_diacritics() {
+        assert_cases(&[
+            ("munchen", Some("Munich, DE")),
+            ("MÜNCHEN", Some("Munich, DE")),
+            ("Muenchen", Some("Munich, DE")),
+            ("zurich", Some("Zurich, CH")),
+            ("  Zürich ", Some("Zurich, CH")),
+        ]);
+    }
+
+    #[test]
+    fn matches_alternate_and_native_names() {
+        assert_cases(&[
+            ("Moskau", Some("Moscow, RU")),
+            ("Москва", Some("Moscow, RU")),
+            ("東京", Some("Tokyo, JP")),
+            ("Parigi", Some("Paris, FR")),
+            ("Monaco di Baviera", Some("Munich, DE")),
+        ]);
+    }
+
+    #[test]
+    fn tolerates_small_typos() {
+        assert_cases(&[
+            ("Muncen", Some("Munich, DE")),
+            ("Pariss", Some("Paris, FR")),
+            ("Frankfrut am Main", Some("Frankfurt, DE")),
+            ("xyz", None),
+            ("Qwertyuiop", None),
+        ]);
+    }
+
+    #[test]
+    fn ranks_equal_matches_by_population() {
+        assert_cases(&[
+            ("Kingston", Some("Kingston, JM")),
+            ("San Jose", Some("San Jose, US")),
+        ]);
+
+        let kingstons: Vec<String> = search("Kingston", 5)
+            .iter()
+            .map(|city| city.display_name())
+            .collect();
+        assert_eq!(kingstons, ["Kingston, JM", "Kingston, NF"]);
+    }
+
+    #[test]
+    fn filters_by_a_trailing_country_code() {
+        assert_cases(&[
+            ("Kingston, NF", Some("Kingston, NF")),
+            ("San Jose, CR", Some("San José, CR")),
+            // No Kingston in the US: the filter is dropped rather than finding nothing.
+            ("Kingston, US", Some("Kingston, JM")),
+            // Not a country code in the list.
+            ("Paris, XX", Some("Paris, FR")),
+        ]);
+    }
+
+    #[test]
+    fn respects_empty_queries_and_limits() {
+        assert!(search("", 5).is_empty());
+        assert!(search(" , DE", 5).is_empty());
+        assert!(search("Paris", 0).is_empty());
+    }
+}
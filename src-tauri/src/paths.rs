@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Matches `identifier` in tauri.conf.json so files land next to the WebView2 profile.
+const APP_IDENTIFIER: &str = "com.winlux.app";
+/// Placing this file next to the executable has the same effect as `--portable`.
+const PORTABLE_MARKER_FILE: &str = "winlux.portable";
+const PORTABLE_ARG: &str = "--portable";
+/// Directory beside the executable that holds everything a portable copy writes.
+const PORTABLE_DATA_DIR: &str = "WinLuxData";
+const PORTABLE_SETTINGS_FILE: &str = "settings.json";
+const PORTABLE_CACHE_DIR: &str = "cache";
+const LOG_DIR: &str = "logs";
+const PORTABLE_WEBVIEW_DIR: &str = "webview";
+
+static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Data directory of a portable copy, or `None` for a regular installation.
+pub fn portable_dir() -> Option<&'static PathBuf> {
+    PORTABLE_DIR.get_or_init(detect_portable_dir).as_ref()
+}
+
+pub fn is_portable() -> bool {
+    portable_dir().is_some()
+}
+
+fn detect_portable_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    let requested = std::env::args().any(|arg| arg == PORTABLE_ARG)
+        || exe_dir.join(PORTABLE_MARKER_FILE).is_file();
+
+    requested.then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+/// JSON settings file used instead of the registry in portable mode.
+pub fn portable_settings_file() -> Option<PathBuf> {
+    portable_dir().map(|dir| dir.join(PORTABLE_SETTINGS_FILE))
+}
+
+/// Per-user directory for disposable data such as the geocoding cache.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = portable_dir() {
+        return Some(dir.join(PORTABLE_CACHE_DIR));
+    }
+
+    std::env::var_os("LOCALAPPDATA").map(|base| PathBuf::from(base).join(APP_IDENTIFIER))
+}
+
+/// Directory for WinLux's own log files.
+pub fn log_dir() -> Option<PathBuf> {
+    match portable_dir() {
+        Some(dir) => Some(dir.join(LOG_DIR)),
+        None => cache_dir().map(|dir| dir.join(LOG_DIR)),
+    }
+}
+
+/// WebView2 profile (cache, cookies and its logs) for portable mode. A regular installation
+/// keeps the WebView2 default under `%LOCALAPPDATA%`.
+pub fn portable_webview_data_dir() -> Option<PathBuf> {
+    portable_dir().map(|dir| dir.join(PORTABLE_WEBVIEW_DIR))
+}
@@ -0,0 +1,122 @@
+use crate::models::{AppError, AppResult, AppSettings, AppSettingsSnapshot, ResetScope};
+use tauri::AppHandle;
+
+/// Runs `run_cleanup` instead of starting the app. Passed by the uninstaller.
+pub const CLEANUP_ARG: &str = "--cleanup";
+/// Together with `--cleanup`, also puts back the theme recorded before WinLux changed it.
+pub const RESTORE_THEME_ARG: &str = "--restore-theme";
+/// Together with `--cleanup`, only removes the Run entries and leaves settings, caches and
+/// logs in place.
+pub const KEEP_DATA_ARG: &str = "--keep-data";
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Puts the settings in `scope` back to their defaults in a single save and emits one
+/// `SETTINGS_CHANGED_EVENT`.
+#[tauri::command]
+pub fn reset_settings(app: AppHandle, scope: ResetScope) -> AppResult<AppSettingsSnapshot> {
+    let before = crate::app_settings::get_app_settings()?;
+    let previous_locale = crate::i18n::current_shared_locale();
+
+    if matches!(scope, ResetScope::All | ResetScope::Startup)
+        && before.startup.enabled
+        && !before.startup.portable
+    {
+        crate::commands::set_startup_enabled_internal(false)?;
+    }
+
+    crate::settings::update(|settings| {
+        let defaults = AppSettings::default();
+        match scope {
+            ResetScope::All => {
+                // Not a preference; the uninstaller still needs it.
+                let original_theme = settings.original_theme.take();
+                *settings = AppSettings {
+                    original_theme,
+                    ..defaults
+                };
+            }
+            ResetScope::Solar => {
+                settings.solar = defaults.solar;
+                // The active saved location mirrors the solar location just cleared.
+                settings.saved_locations.active_id = None;
+            }
+            ResetScope::Language => settings.language_preference = defaults.language_preference,
+            ResetScope::Startup => {}
+        }
+        Ok(())
+    })?;
+
+    if matches!(scope, ResetScope::All | ResetScope::Language) {
+        if crate::i18n::current_shared_locale() != previous_locale {
+            crate::locations::spawn_location_relocalization(app.clone());
+        }
+        crate::tray::refresh_tray_language()
+            .map_err(|error| err_with_source("errors.tray.refresh_language_failed", error))?;
+    }
+    if scope == ResetScope::All {
+        crate::http::reset_shared_client();
+        crate::location_provider::notify_location_auto_update_worker();
+        crate::network::reevaluate_network_location();
+    }
+    if matches!(scope, ResetScope::All | ResetScope::Solar) {
+        crate::commands::refresh_auto_theme(&app)?;
+    }
+
+    crate::app_settings::emit_settings_changed(&app, &before)
+}
+
+/// Removes what WinLux stored for the current user: Run entries that start this executable
+/// and, unless `keep_data` is set, settings, the geocoding cache and logs. Must run before
+/// the executable is deleted, since Run entries are matched by its path. Keeps going after
+/// a failed step and returns the first error.
+pub fn run_cleanup(restore_theme: bool, keep_data: bool) -> AppResult<()> {
+    let mut first_error = None;
+    let mut step = |result: AppResult<()>| {
+        if let Err(error) = result {
+            first_error.get_or_insert(error);
+        }
+    };
+
+    if restore_theme {
+        step(restore_original_theme());
+    }
+    if !crate::paths::is_portable() {
+        step(crate::commands::set_startup_enabled_internal(false));
+    }
+    if keep_data {
+        return first_error.map_or(Ok(()), Err);
+    }
+
+    step(crate::settings::clear());
+    step(crate::geocode_cache::clear_geocode_cache());
+    step(remove_logs());
+
+    // Only removed once empty; the WebView2 profile may live there too.
+    if let Some(cache_dir) = crate::paths::cache_dir() {
+        let _ = std::fs::remove_dir(cache_dir);
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+fn restore_original_theme() -> AppResult<()> {
+    match crate::settings::load()?.original_theme {
+        Some(theme) => crate::commands::write_theme_state(&theme),
+        None => Ok(()),
+    }
+}
+
+fn remove_logs() -> AppResult<()> {
+    let Some(log_dir) = crate::paths::log_dir() else {
+        return Ok(());
+    };
+
+    match std::fs::remove_dir_all(log_dir) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(err_with_source("errors.logs.clear_failed", error)),
+    }
+}
@@ -0,0 +1,69 @@
+use crate::models::{AppError, AppResult};
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Encrypts `text` with DPAPI for the current Windows user, so secrets such as the proxy
+/// password never reach the settings store in plain text. Only the same user on the same
+/// machine can read the result back.
+pub fn protect(text: &str) -> AppResult<Vec<u8>> {
+    crypt(text.as_bytes(), true)
+        .map_err(|error| err_with_source("errors.settings.save_failed", error))
+}
+
+/// Decrypts a blob produced by [`protect`].
+pub fn unprotect(blob: &[u8]) -> AppResult<String> {
+    let bytes = crypt(blob, false)
+        .map_err(|error| err_with_source("errors.settings.load_failed", error))?;
+    String::from_utf8(bytes).map_err(|error| err_with_source("errors.settings.load_failed", error))
+}
+
+fn crypt(input: &[u8], encrypt: bool) -> std::io::Result<Vec<u8>> {
+    use std::ptr;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    let input_blob = CRYPT_INTEGER_BLOB {
+        cbData: input.len() as u32,
+        pbData: input.as_ptr() as *mut u8,
+    };
+    let mut output_blob = CRYPT_INTEGER_BLOB {
+        cbData: 0,
+        pbData: ptr::null_mut(),
+    };
+
+    unsafe {
+        let succeeded = if encrypt {
+            CryptProtectData(
+                &input_blob,
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output_blob,
+            )
+        } else {
+            CryptUnprotectData(
+                &input_blob,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output_blob,
+            )
+        };
+        if succeeded == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let output =
+            std::slice::from_raw_parts(output_blob.pbData, output_blob.cbData as usize).to_vec();
+        LocalFree(output_blob.pbData as _);
+        Ok(output)
+    }
+}
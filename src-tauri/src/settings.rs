@@ -0,0 +1,1087 @@
+use crate::models::{
+    AppError, AppResult, AppSettings, GeocodeResult, GeocoderProvider, LocationAutoUpdateSettings,
+    LocationSource, SavedLocation, SavedLocations, SettingsIssue, StoredGeocoderSettings,
+    StoredIpLocationSettings, StoredProxySettings, StoredSolarSettings, ThemeMode, ThemeState,
+};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+const SETTINGS_KEY: &str = "Software\\WinLux";
+const LOCATIONS_KEY: &str = "Software\\WinLux\\Locations";
+const SETTINGS_VALUE_SCHEMA_VERSION: &str = "SchemaVersion";
+const SETTINGS_VALUE_SOLAR_ADDRESS: &str = "SolarAddress";
+const SETTINGS_VALUE_SOLAR_DISPLAY_NAME: &str = "SolarDisplayName";
+const SETTINGS_VALUE_SOLAR_LATITUDE: &str = "SolarLatitude";
+const SETTINGS_VALUE_SOLAR_LONGITUDE: &str = "SolarLongitude";
+const SETTINGS_VALUE_SOLAR_LOCATION_SOURCE: &str = "SolarLocationSource";
+const SETTINGS_VALUE_SOLAR_TIME_ZONE: &str = "SolarTimeZone";
+const SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED: &str = "SolarAutoThemeEnabled";
+const SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES: &str = "SolarSunsetOffsetMinutes";
+const SETTINGS_VALUE_DRY_RUN_ENABLED: &str = "DryRunEnabled";
+const SETTINGS_VALUE_PREFER_OFFLINE_GEOCODING: &str = "PreferOfflineGeocoding";
+const SETTINGS_VALUE_LANGUAGE_PREFERENCE: &str = "LanguagePreference";
+const SETTINGS_VALUE_GEOCODER_PROVIDER: &str = "GeocoderProvider";
+const SETTINGS_VALUE_GEOCODER_ENDPOINT: &str = "GeocoderEndpoint";
+const SETTINGS_VALUE_IP_LOCATION_ENABLED: &str = "IpLocationEnabled";
+const SETTINGS_VALUE_IP_LOCATION_ENDPOINT: &str = "IpLocationEndpoint";
+const SETTINGS_VALUE_LOCATION_AUTO_UPDATE_ENABLED: &str = "LocationAutoUpdateEnabled";
+const SETTINGS_VALUE_LOCATION_AUTO_UPDATE_INTERVAL_HOURS: &str = "LocationAutoUpdateIntervalHours";
+const SETTINGS_VALUE_LOCATION_AUTO_UPDATE_MIN_DISTANCE_KM: &str = "LocationAutoUpdateMinDistanceKm";
+const SETTINGS_VALUE_LOCATION_AUTO_UPDATE_LAST_CHECK: &str = "LocationAutoUpdateLastCheck";
+const SETTINGS_VALUE_PROXY_URL: &str = "ProxyUrl";
+const SETTINGS_VALUE_PROXY_USERNAME: &str = "ProxyUsername";
+/// REG_BINARY encrypted with `secret::protect`; empty when no password is set.
+const SETTINGS_VALUE_PROXY_PASSWORD: &str = "ProxyPassword";
+const SETTINGS_VALUE_ACTIVE_LOCATION_ID: &str = "ActiveLocationId";
+const SETTINGS_VALUE_ORIGINAL_APPS_THEME: &str = "OriginalAppsTheme";
+const SETTINGS_VALUE_ORIGINAL_SYSTEM_THEME: &str = "OriginalSystemTheme";
+const LOCATION_VALUE_NAME: &str = "Name";
+const LOCATION_VALUE_ADDRESS: &str = "Address";
+const LOCATION_VALUE_DISPLAY_NAME: &str = "DisplayName";
+const LOCATION_VALUE_LATITUDE: &str = "Latitude";
+const LOCATION_VALUE_LONGITUDE: &str = "Longitude";
+const LOCATION_VALUE_SOURCE: &str = "Source";
+/// REG_MULTI_SZ of `<kind>:<value>` entries, e.g. `ssid:Office` or `dns:corp.example.com`.
+const LOCATION_VALUE_NETWORK_RULES: &str = "NetworkRules";
+/// Path of a JSON file to keep settings in instead of the registry.
+const SETTINGS_FILE_ENV: &str = "WINLUX_SETTINGS_FILE";
+/// Hex of the `secret::protect` blob, written in place of `proxy.password` in JSON files.
+const JSON_FIELD_PROTECTED_PASSWORD: &str = "protected_password";
+const JSON_FIELD_PASSWORD: &str = "password";
+const JSON_FIELD_PROXY: &str = "proxy";
+const JSON_FIELD_SUNSET_OFFSET_MINUTES: &str = "solar.sunset_offset_minutes";
+
+static STORE: OnceLock<Box<dyn SettingsStore>> = OnceLock::new();
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Where `AppSettings` live. Implementations load missing values as their defaults and
+/// save the whole document.
+pub trait SettingsStore: Send + Sync {
+    /// Also returns stored values that were present but unusable, which were loaded as
+    /// their defaults instead.
+    fn load_with_issues(&self) -> AppResult<(AppSettings, Vec<SettingsIssue>)>;
+    fn save(&self, settings: &AppSettings) -> AppResult<()>;
+    /// Removes everything the store holds, leaving nothing behind on disk.
+    fn clear(&self) -> AppResult<()>;
+
+    fn load(&self) -> AppResult<AppSettings> {
+        Ok(self.load_with_issues()?.0)
+    }
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            schema_version: crate::settings_schema::SETTINGS_SCHEMA_VERSION,
+            solar: StoredSolarSettings::default(),
+            language_preference: crate::i18n::LANGUAGE_PREFERENCE_AUTO.to_string(),
+            geocoder: StoredGeocoderSettings::default(),
+            ip_location: StoredIpLocationSettings::default(),
+            location_auto_update: LocationAutoUpdateSettings::default(),
+            proxy: StoredProxySettings::default(),
+            saved_locations: SavedLocations::default(),
+            original_theme: None,
+        }
+    }
+}
+
+/// Replaces the default store. Only takes effect before settings are first accessed.
+pub fn install_store(store: Box<dyn SettingsStore>) -> bool {
+    STORE.set(store).is_ok()
+}
+
+fn store() -> &'static dyn SettingsStore {
+    STORE
+        .get_or_init(|| match std::env::var_os(SETTINGS_FILE_ENV) {
+            Some(path) if !path.is_empty() => {
+                Box::new(JsonFileSettingsStore::new(PathBuf::from(path)))
+            }
+            _ => Box::new(RegistrySettingsStore),
+        })
+        .as_ref()
+}
+
+pub fn load() -> AppResult<AppSettings> {
+    store().load()
+}
+
+pub fn load_with_issues() -> AppResult<(AppSettings, Vec<SettingsIssue>)> {
+    store().load_with_issues()
+}
+
+/// Deletes the stored settings. Later loads return the defaults.
+pub fn clear() -> AppResult<()> {
+    let _guard = match UPDATE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    store().clear()
+}
+
+/// Applies `change` to the stored settings and saves them if anything changed. Updates
+/// are serialized so concurrent read-modify-write cycles can't drop each other's changes.
+pub fn update(change: impl FnOnce(&mut AppSettings) -> AppResult<()>) -> AppResult<AppSettings> {
+    let _guard = match UPDATE_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    update_store(store(), change)
+}
+
+fn update_store(
+    store: &dyn SettingsStore,
+    change: impl FnOnce(&mut AppSettings) -> AppResult<()>,
+) -> AppResult<AppSettings> {
+    let mut settings = store.load()?;
+    let previous = settings.clone();
+    change(&mut settings)?;
+    if settings != previous {
+        store.save(&settings)?;
+    }
+
+    Ok(settings)
+}
+
+/// Values under `HKCU\Software\WinLux`, with saved locations as subkeys of `Locations`.
+pub struct RegistrySettingsStore;
+
+impl SettingsStore for RegistrySettingsStore {
+    fn load_with_issues(&self) -> AppResult<(AppSettings, Vec<SettingsIssue>)> {
+        use winreg::enums::{HKEY_CURRENT_USER, KEY_READ};
+        use winreg::RegKey;
+
+        let defaults = AppSettings::default();
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let Ok(key) = hkcu.open_subkey_with_flags(SETTINGS_KEY, KEY_READ) else {
+            return Ok((defaults, Vec::new()));
+        };
+
+        let mut values = RegistryValues::new(&key, "");
+        let location = values.location(
+            SETTINGS_VALUE_SOLAR_ADDRESS,
+            SETTINGS_VALUE_SOLAR_DISPLAY_NAME,
+            SETTINGS_VALUE_SOLAR_LATITUDE,
+            SETTINGS_VALUE_SOLAR_LONGITUDE,
+            SETTINGS_VALUE_SOLAR_LOCATION_SOURCE,
+        );
+        let geocoder_provider = values.string(SETTINGS_VALUE_GEOCODER_PROVIDER);
+        if !geocoder_provider.trim().is_empty()
+            && GeocoderProvider::from_value(&geocoder_provider).as_str() != geocoder_provider.trim()
+        {
+            values.report(SETTINGS_VALUE_GEOCODER_PROVIDER, &geocoder_provider);
+        }
+        let last_checked_at = values.string(SETTINGS_VALUE_LOCATION_AUTO_UPDATE_LAST_CHECK);
+        let active_location_id = values.string(SETTINGS_VALUE_ACTIVE_LOCATION_ID);
+
+        let settings = AppSettings {
+            // Settings written before `SchemaVersion` existed have none.
+            schema_version: values
+                .get(SETTINGS_VALUE_SCHEMA_VERSION)
+                .unwrap_or(crate::settings_schema::legacy_schema_version()),
+            solar: StoredSolarSettings {
+                location,
+                time_zone: values
+                    .string(SETTINGS_VALUE_SOLAR_TIME_ZONE)
+                    .trim()
+                    .to_string(),
+                auto_theme_enabled: values.flag(SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED),
+                sunset_offset_minutes: values
+                    .sunset_offset(SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES),
+                dry_run_enabled: values.flag(SETTINGS_VALUE_DRY_RUN_ENABLED),
+                prefer_offline_geocoding: values.flag(SETTINGS_VALUE_PREFER_OFFLINE_GEOCODING),
+            },
+            language_preference: values
+                .get(SETTINGS_VALUE_LANGUAGE_PREFERENCE)
+                .unwrap_or(defaults.language_preference),
+            geocoder: StoredGeocoderSettings {
+                provider: GeocoderProvider::from_value(&geocoder_provider),
+                endpoint: values.string(SETTINGS_VALUE_GEOCODER_ENDPOINT),
+            },
+            ip_location: StoredIpLocationSettings {
+                enabled: values.flag(SETTINGS_VALUE_IP_LOCATION_ENABLED),
+                endpoint: values.string(SETTINGS_VALUE_IP_LOCATION_ENDPOINT),
+            },
+            location_auto_update: LocationAutoUpdateSettings {
+                enabled: values.flag(SETTINGS_VALUE_LOCATION_AUTO_UPDATE_ENABLED),
+                interval_hours: values
+                    .get(SETTINGS_VALUE_LOCATION_AUTO_UPDATE_INTERVAL_HOURS)
+                    .unwrap_or(defaults.location_auto_update.interval_hours),
+                min_distance_km: values
+                    .get(SETTINGS_VALUE_LOCATION_AUTO_UPDATE_MIN_DISTANCE_KM)
+                    .unwrap_or(defaults.location_auto_update.min_distance_km),
+                last_checked_at: (!last_checked_at.is_empty()).then_some(last_checked_at),
+            },
+            proxy: StoredProxySettings {
+                url: values.string(SETTINGS_VALUE_PROXY_URL),
+                username: values.string(SETTINGS_VALUE_PROXY_USERNAME),
+                password: values.secret(SETTINGS_VALUE_PROXY_PASSWORD),
+            },
+            saved_locations: read_saved_locations(active_location_id, &mut values.issues),
+            original_theme: values.theme(
+                SETTINGS_VALUE_ORIGINAL_APPS_THEME,
+                SETTINGS_VALUE_ORIGINAL_SYSTEM_THEME,
+            ),
+        };
+
+        Ok((settings, values.issues))
+    }
+
+    /// Writes everything in one registry transaction. Any failure before the commit drops
+    /// the transaction, which rolls it back, so readers see either the old or the new
+    /// settings and never a mix of both.
+    fn save(&self, settings: &AppSettings) -> AppResult<()> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        use winreg::transaction::Transaction;
+        use winreg::RegKey;
+
+        let transaction = Transaction::new()
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        let (key, _) = hkcu
+            .create_subkey_transacted(SETTINGS_KEY, &transaction)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+
+        let solar = &settings.solar;
+        let (address, display_name, latitude, longitude, source) = match &solar.location {
+            Some(location) => (
+                location.address.clone(),
+                location.display_name.clone(),
+                location.latitude.to_string(),
+                location.longitude.to_string(),
+                location.source.as_str(),
+            ),
+            None => (
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                "",
+            ),
+        };
+        let sunset_offset_minutes = u32::try_from(solar.sunset_offset_minutes)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        let auto_update = &settings.location_auto_update;
+
+        set_value(
+            &key,
+            SETTINGS_VALUE_SCHEMA_VERSION,
+            &settings.schema_version,
+        )?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_ADDRESS, &address)?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_DISPLAY_NAME, &display_name)?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_LATITUDE, &latitude)?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_LONGITUDE, &longitude)?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_LOCATION_SOURCE, &source)?;
+        set_value(&key, SETTINGS_VALUE_SOLAR_TIME_ZONE, &solar.time_zone)?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_SOLAR_AUTO_THEME_ENABLED,
+            &u32::from(solar.auto_theme_enabled),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_SOLAR_SUNSET_OFFSET_MINUTES,
+            &sunset_offset_minutes,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_DRY_RUN_ENABLED,
+            &u32::from(solar.dry_run_enabled),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_PREFER_OFFLINE_GEOCODING,
+            &u32::from(solar.prefer_offline_geocoding),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_LANGUAGE_PREFERENCE,
+            &settings.language_preference,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_GEOCODER_PROVIDER,
+            &settings.geocoder.provider.as_str(),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_GEOCODER_ENDPOINT,
+            &settings.geocoder.endpoint,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_IP_LOCATION_ENABLED,
+            &u32::from(settings.ip_location.enabled),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_IP_LOCATION_ENDPOINT,
+            &settings.ip_location.endpoint,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_LOCATION_AUTO_UPDATE_ENABLED,
+            &u32::from(auto_update.enabled),
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_LOCATION_AUTO_UPDATE_INTERVAL_HOURS,
+            &auto_update.interval_hours,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_LOCATION_AUTO_UPDATE_MIN_DISTANCE_KM,
+            &auto_update.min_distance_km,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_LOCATION_AUTO_UPDATE_LAST_CHECK,
+            &auto_update.last_checked_at.clone().unwrap_or_default(),
+        )?;
+        set_value(&key, SETTINGS_VALUE_PROXY_URL, &settings.proxy.url)?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_PROXY_USERNAME,
+            &settings.proxy.username,
+        )?;
+        let password = match settings.proxy.password.as_str() {
+            "" => Vec::new(),
+            password => crate::secret::protect(password)?,
+        };
+        key.set_raw_value(
+            SETTINGS_VALUE_PROXY_PASSWORD,
+            &winreg::RegValue {
+                bytes: password,
+                vtype: winreg::enums::REG_BINARY,
+            },
+        )
+        .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        let (original_apps_theme, original_system_theme) = match &settings.original_theme {
+            Some(theme) => (theme.apps.as_str(), theme.system.as_str()),
+            None => ("", ""),
+        };
+        set_value(
+            &key,
+            SETTINGS_VALUE_ORIGINAL_APPS_THEME,
+            &original_apps_theme,
+        )?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_ORIGINAL_SYSTEM_THEME,
+            &original_system_theme,
+        )?;
+
+        write_saved_locations(&transaction, &settings.saved_locations.locations)?;
+        set_value(
+            &key,
+            SETTINGS_VALUE_ACTIVE_LOCATION_ID,
+            &settings
+                .saved_locations
+                .active_id
+                .clone()
+                .unwrap_or_default(),
+        )?;
+
+        transaction
+            .commit()
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))
+    }
+
+    fn clear(&self) -> AppResult<()> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        use winreg::RegKey;
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        match hkcu.delete_subkey_all(SETTINGS_KEY) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(err_with_source("errors.settings.clear_failed", error)),
+        }
+    }
+}
+
+/// The whole document as one JSON file. Saves go through a temporary file so a crash
+/// never leaves a truncated document behind.
+pub struct JsonFileSettingsStore {
+    path: PathBuf,
+}
+
+impl JsonFileSettingsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl SettingsStore for JsonFileSettingsStore {
+    /// Like the registry store, a value of the wrong type is reported and loaded as its
+    /// default; only a file that isn't a JSON object fails the whole load.
+    fn load_with_issues(&self) -> AppResult<(AppSettings, Vec<SettingsIssue>)> {
+        let load_failed =
+            |error: serde_json::Error| err_with_source("errors.settings.load_failed", error);
+        let mut document: serde_json::Value = match std::fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(load_failed)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((AppSettings::default(), Vec::new()))
+            }
+            Err(error) => return Err(err_with_source("errors.settings.load_failed", error)),
+        };
+        if !document.is_object() {
+            return Err(AppError::new("errors.settings.load_failed")
+                .with_param("source", "the settings file is not a JSON object"));
+        }
+
+        let mut issues = Vec::new();
+        let protected_password = document
+            .get_mut(JSON_FIELD_PROXY)
+            .and_then(serde_json::Value::as_object_mut)
+            .and_then(|proxy| proxy.remove(JSON_FIELD_PROTECTED_PASSWORD));
+
+        // Start from the defaults and take the file's values one field at a time, so a bad
+        // value only costs that field.
+        let mut merged = serde_json::to_value(AppSettings::default()).map_err(load_failed)?;
+        let mut fields = Vec::new();
+        collect_json_fields(&merged, document, &mut Vec::new(), &mut fields);
+        for (path, value) in fields {
+            let previous = set_json_field(&mut merged, &path, value.clone());
+            if serde_json::from_value::<AppSettings>(merged.clone()).is_err() {
+                match previous {
+                    Some(previous) => set_json_field(&mut merged, &path, previous),
+                    None => remove_json_field(&mut merged, &path),
+                };
+                issues.push(invalid_value(&path.join("."), &value.to_string()));
+            }
+        }
+        let mut settings: AppSettings = serde_json::from_value(merged).map_err(load_failed)?;
+
+        if let Some(protected_password) = protected_password {
+            match unprotect_hex(&protected_password) {
+                Some(password) => settings.proxy.password = password,
+                None => issues.push(invalid_value(
+                    &format!("{JSON_FIELD_PROXY}.{JSON_FIELD_PROTECTED_PASSWORD}"),
+                    &protected_password.to_string(),
+                )),
+            }
+        }
+        if let Err(error) = check_sunset_offset(settings.solar.sunset_offset_minutes) {
+            settings.solar.sunset_offset_minutes = 0;
+            issues.push(invalid_value(JSON_FIELD_SUNSET_OFFSET_MINUTES, &error));
+        }
+
+        Ok((settings, issues))
+    }
+
+    fn save(&self, settings: &AppSettings) -> AppResult<()> {
+        let save_failed =
+            |error: std::io::Error| err_with_source("errors.settings.save_failed", error);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(save_failed)?;
+        }
+
+        let mut document = serde_json::to_value(settings)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        if let Some(proxy) = document
+            .get_mut(JSON_FIELD_PROXY)
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            proxy.remove(JSON_FIELD_PASSWORD);
+            if !settings.proxy.password.is_empty() {
+                let blob = crate::secret::protect(&settings.proxy.password)?;
+                proxy.insert(
+                    JSON_FIELD_PROTECTED_PASSWORD.to_string(),
+                    serde_json::Value::String(encode_hex(&blob)),
+                );
+            }
+        }
+        let bytes = serde_json::to_vec_pretty(&document)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, bytes).map_err(save_failed)?;
+        std::fs::rename(&temp_path, &self.path).map_err(save_failed)
+    }
+
+    fn clear(&self) -> AppResult<()> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(err_with_source("errors.settings.clear_failed", error)),
+        }
+    }
+}
+
+/// Splits `document` into the values to merge onto `template`: nested objects are followed
+/// as long as `template` has an object at the same path, anything else is one field.
+fn collect_json_fields(
+    template: &serde_json::Value,
+    document: serde_json::Value,
+    path: &mut Vec<String>,
+    fields: &mut Vec<(Vec<String>, serde_json::Value)>,
+) {
+    match (template.as_object(), document) {
+        (Some(template), serde_json::Value::Object(document)) => {
+            for (key, value) in document {
+                let child = template.get(&key);
+                path.push(key);
+                match child {
+                    Some(child) => collect_json_fields(child, value, path, fields),
+                    None => fields.push((path.clone(), value)),
+                }
+                path.pop();
+            }
+        }
+        (_, document) => fields.push((path.clone(), document)),
+    }
+}
+
+/// Returns the value `path` held before, if any.
+fn set_json_field(
+    root: &mut serde_json::Value,
+    path: &[String],
+    value: serde_json::Value,
+) -> Option<serde_json::Value> {
+    let (key, parents) = path.split_last()?;
+    let parent = parents
+        .iter()
+        .try_fold(root, |node, parent| node.get_mut(parent))?;
+    parent.as_object_mut()?.insert(key.clone(), value)
+}
+
+fn remove_json_field(root: &mut serde_json::Value, path: &[String]) -> Option<serde_json::Value> {
+    let (key, parents) = path.split_last()?;
+    let parent = parents
+        .iter()
+        .try_fold(root, |node, parent| node.get_mut(parent))?;
+    parent.as_object_mut()?.remove(key)
+}
+
+/// Stored offsets outside the range the setters accept are reported rather than used.
+fn check_sunset_offset(minutes: i64) -> Result<(), String> {
+    crate::commands::normalize_sunset_offset_minutes(minutes)
+        .map(|_| ())
+        .map_err(|_| minutes.to_string())
+}
+
+fn invalid_value(field: &str, value: &str) -> SettingsIssue {
+    SettingsIssue {
+        field: field.to_string(),
+        error: AppError::new("errors.settings.invalid_value")
+            .with_param("field", field)
+            .with_param("value", value),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unprotect_hex(value: &serde_json::Value) -> Option<String> {
+    let text = value.as_str()?;
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    let blob = (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    crate::secret::unprotect(&blob).ok()
+}
+
+fn set_value<T: winreg::types::ToRegValue>(
+    key: &winreg::RegKey,
+    name: &str,
+    value: &T,
+) -> AppResult<()> {
+    key.set_value(name, value)
+        .map_err(|error| err_with_source("errors.settings.save_failed", error))
+}
+
+/// Reads values under one key, reporting values that exist but can't be used instead of
+/// quietly falling back to their defaults.
+struct RegistryValues<'a> {
+    key: &'a winreg::RegKey,
+    /// Prepended to value names in reports, e.g. `Locations\loc-1\`.
+    prefix: &'a str,
+    issues: Vec<SettingsIssue>,
+}
+
+impl<'a> RegistryValues<'a> {
+    fn new(key: &'a winreg::RegKey, prefix: &'a str) -> Self {
+        Self {
+            key,
+            prefix,
+            issues: Vec::new(),
+        }
+    }
+
+    fn get<T: winreg::types::FromRegValue>(&mut self, name: &str) -> Option<T> {
+        match self.key.get_value::<T, _>(name) {
+            Ok(value) => Some(value),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => {
+                self.report(name, &error.to_string());
+                None
+            }
+        }
+    }
+
+    fn string(&mut self, name: &str) -> String {
+        self.get(name).unwrap_or_default()
+    }
+
+    /// Decrypts a value written with `secret::protect`. A plain string from before the
+    /// password was protected is read as is; the next save protects it.
+    fn secret(&mut self, name: &str) -> String {
+        let raw = match self.key.get_raw_value(name) {
+            Ok(raw) => raw,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return String::new(),
+            Err(error) => {
+                self.report(name, &error.to_string());
+                return String::new();
+            }
+        };
+        if raw.vtype != winreg::enums::REG_BINARY {
+            return self.string(name);
+        }
+        if raw.bytes.is_empty() {
+            return String::new();
+        }
+
+        crate::secret::unprotect(&raw.bytes).unwrap_or_else(|error| {
+            self.report(name, error.params.get("source").map_or("", String::as_str));
+            String::new()
+        })
+    }
+
+    fn sunset_offset(&mut self, name: &str) -> i64 {
+        let minutes = i64::from(self.get::<u32>(name).unwrap_or(0));
+        match check_sunset_offset(minutes) {
+            Ok(()) => minutes,
+            Err(value) => {
+                self.report(name, &value);
+                0
+            }
+        }
+    }
+
+    fn flag(&mut self, name: &str) -> bool {
+        self.get::<u32>(name).unwrap_or(0) != 0
+    }
+
+    fn coordinate(&mut self, name: &str) -> Option<f64> {
+        let raw = self.string(name);
+        match raw.trim().parse::<f64>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.report(name, &raw);
+                None
+            }
+        }
+    }
+
+    /// `None` when no location is stored or any of its parts is unusable.
+    fn location(
+        &mut self,
+        address_name: &str,
+        display_name_name: &str,
+        latitude_name: &str,
+        longitude_name: &str,
+        source_name: &str,
+    ) -> Option<GeocodeResult> {
+        let address = self.string(address_name);
+        let display_name = self.string(display_name_name);
+        if address.trim().is_empty() || display_name.trim().is_empty() {
+            return None;
+        }
+
+        let latitude = self.coordinate(latitude_name);
+        let longitude = self.coordinate(longitude_name);
+        let source = self.string(source_name);
+        if !source.trim().is_empty()
+            && LocationSource::from_value(&source).as_str() != source.trim()
+        {
+            self.report(source_name, &source);
+        }
+
+        Some(GeocodeResult {
+            address,
+            display_name,
+            latitude: latitude?,
+            longitude: longitude?,
+            source: LocationSource::from_value(&source),
+        })
+    }
+
+    /// `None` unless both values hold a theme.
+    fn theme(&mut self, apps_name: &str, system_name: &str) -> Option<ThemeState> {
+        let mut mode = |name: &str| {
+            let raw = self.string(name);
+            let mode = ThemeMode::from_value(&raw);
+            if mode.is_none() && !raw.trim().is_empty() {
+                self.report(name, &raw);
+            }
+            mode
+        };
+        let apps = mode(apps_name);
+        let system = mode(system_name);
+
+        Some(ThemeState {
+            apps: apps?,
+            system: system?,
+        })
+    }
+
+    fn report(&mut self, name: &str, value: &str) {
+        let field = format!("{}{name}", self.prefix);
+        self.issues.push(invalid_value(&field, value));
+    }
+}
+
+fn read_saved_locations(active_id: String, issues: &mut Vec<SettingsIssue>) -> SavedLocations {
+    use winreg::enums::{HKEY_CURRENT_USER, KEY_READ};
+    use winreg::RegKey;
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let Ok(locations_key) = hkcu.open_subkey_with_flags(LOCATIONS_KEY, KEY_READ) else {
+        return SavedLocations::default();
+    };
+
+    let mut locations = Vec::new();
+    for id in locations_key.enum_keys().filter_map(Result::ok) {
+        let Ok(entry_key) = locations_key.open_subkey_with_flags(&id, KEY_READ) else {
+            continue;
+        };
+        let prefix = format!("Locations\\{id}\\");
+        let mut values = RegistryValues::new(&entry_key, &prefix);
+
+        let name = values.string(LOCATION_VALUE_NAME);
+        if name.trim().is_empty() {
+            values.report(LOCATION_VALUE_NAME, &name);
+        }
+        let location = values.location(
+            LOCATION_VALUE_ADDRESS,
+            LOCATION_VALUE_DISPLAY_NAME,
+            LOCATION_VALUE_LATITUDE,
+            LOCATION_VALUE_LONGITUDE,
+            LOCATION_VALUE_SOURCE,
+        );
+        if location.is_none() && values.issues.is_empty() {
+            // Broken coordinates are reported while reading them; what's left is a
+            // missing address.
+            values.report(LOCATION_VALUE_ADDRESS, "");
+        }
+
+        let raw_rules: Vec<String> = values.get(LOCATION_VALUE_NETWORK_RULES).unwrap_or_default();
+        let mut network_rules = Vec::new();
+        for raw in &raw_rules {
+            match crate::network::parse_network_rule(raw) {
+                Some(rule) => network_rules.push(rule),
+                None => values.report(LOCATION_VALUE_NETWORK_RULES, raw),
+            }
+        }
+
+        issues.append(&mut values.issues);
+        let Some(location) = location.filter(|_| !name.trim().is_empty()) else {
+            continue;
+        };
+
+        locations.push(SavedLocation {
+            id,
+            name,
+            location,
+            network_rules,
+        });
+    }
+
+    let active_id = Some(active_id)
+        .filter(|id| !id.trim().is_empty())
+        .filter(|id| locations.iter().any(|saved| &saved.id == id));
+
+    SavedLocations {
+        locations,
+        active_id,
+    }
+}
+
+/// Writes every location to its own subkey and removes subkeys of deleted locations.
+fn write_saved_locations(
+    transaction: &winreg::transaction::Transaction,
+    locations: &[SavedLocation],
+) -> AppResult<()> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (locations_key, _) = hkcu
+        .create_subkey_transacted(LOCATIONS_KEY, transaction)
+        .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+
+    let removed: Vec<String> = locations_key
+        .enum_keys()
+        .filter_map(Result::ok)
+        .filter(|id| !locations.iter().any(|saved| &saved.id == id))
+        .collect();
+    // Location subkeys only hold values, so the transacted non-recursive delete suffices.
+    for id in removed {
+        locations_key
+            .delete_subkey_transacted(&id, transaction)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+    }
+
+    for saved in locations {
+        let (key, _) = locations_key
+            .create_subkey_transacted(&saved.id, transaction)
+            .map_err(|error| err_with_source("errors.settings.save_failed", error))?;
+        let network_rules: Vec<String> = saved
+            .network_rules
+            .iter()
+            .map(crate::network::format_network_rule)
+            .collect();
+
+        set_value(&key, LOCATION_VALUE_NAME, &saved.name)?;
+        set_value(&key, LOCATION_VALUE_ADDRESS, &saved.location.address)?;
+        set_value(
+            &key,
+            LOCATION_VALUE_DISPLAY_NAME,
+            &saved.location.display_name,
+        )?;
+        set_value(
+            &key,
+            LOCATION_VALUE_LATITUDE,
+            &saved.location.latitude.to_string(),
+        )?;
+        set_value(
+            &key,
+            LOCATION_VALUE_LONGITUDE,
+            &saved.location.longitude.to_string(),
+        )?;
+        set_value(&key, LOCATION_VALUE_SOURCE, &saved.location.source.as_str())?;
+        set_value(&key, LOCATION_VALUE_NETWORK_RULES, &network_rules)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Keeps the document in memory and counts saves.
+    #[derive(Default)]
+    struct MemorySettingsStore {
+        settings: Mutex<Option<AppSettings>>,
+        saves: AtomicUsize,
+    }
+
+    impl SettingsStore for MemorySettingsStore {
+        fn load_with_issues(&self) -> AppResult<(AppSettings, Vec<SettingsIssue>)> {
+            let settings = self.settings.lock().unwrap().clone().unwrap_or_default();
+            Ok((settings, Vec::new()))
+        }
+
+        fn save(&self, settings: &AppSettings) -> AppResult<()> {
+            self.saves.fetch_add(1, Ordering::SeqCst);
+            *self.settings.lock().unwrap() = Some(settings.clone());
+            Ok(())
+        }
+
+        fn clear(&self) -> AppResult<()> {
+            *self.settings.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    /// A JSON store on a file of its own under the temp directory, removed on drop.
+    struct TempJsonStore {
+        store: JsonFileSettingsStore,
+        path: PathBuf,
+    }
+
+    impl TempJsonStore {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "winlux-settings-{}-{name}.json",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            Self {
+                store: JsonFileSettingsStore::new(path.clone()),
+                path,
+            }
+        }
+
+        fn with_contents(name: &str, contents: &str) -> Self {
+            let store = Self::new(name);
+            std::fs::write(&store.path, contents).unwrap();
+            store
+        }
+    }
+
+    impl Drop for TempJsonStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn update_saves_changes_and_returns_them() {
+        let store = MemorySettingsStore::default();
+
+        let updated = update_store(&store, |settings| {
+            settings.solar.auto_theme_enabled = true;
+            settings.solar.sunset_offset_minutes = 30;
+            Ok(())
+        })
+        .unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded, updated);
+        assert!(loaded.solar.auto_theme_enabled);
+        assert_eq!(loaded.solar.sunset_offset_minutes, 30);
+        assert_eq!(store.saves.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn update_skips_the_save_when_nothing_changed() {
+        let store = MemorySettingsStore::default();
+
+        update_store(&store, |_| Ok(())).unwrap();
+        update_store(&store, |settings| {
+            settings.solar.dry_run_enabled = false;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(store.saves.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn update_discards_the_change_when_it_fails() {
+        let store = MemorySettingsStore::default();
+
+        let result = update_store(&store, |settings| {
+            settings.language_preference = "de-DE".to_string();
+            Err(AppError::new("errors.test.rejected"))
+        });
+
+        assert_eq!(result.unwrap_err().code, "errors.test.rejected");
+        assert_eq!(store.saves.load(Ordering::SeqCst), 0);
+        assert_eq!(store.load().unwrap(), AppSettings::default());
+    }
+
+    #[test]
+    fn json_store_round_trips_through_update() {
+        let json = TempJsonStore::new("round-trip");
+
+        let updated = update_store(&json.store, |settings| {
+            settings.language_preference = "fr-FR".to_string();
+            settings.geocoder.endpoint = "https://geo.example.com".to_string();
+            settings.location_auto_update.last_checked_at = Some("2026-01-01T00:00:00Z".into());
+            settings.original_theme = Some(ThemeState {
+                apps: ThemeMode::Dark,
+                system: ThemeMode::Light,
+            });
+            Ok(())
+        })
+        .unwrap();
+
+        let (loaded, issues) = json.store.load_with_issues().unwrap();
+        assert_eq!(loaded, updated);
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn json_store_never_writes_the_proxy_password_in_plain_text() {
+        let json = TempJsonStore::new("proxy-password");
+
+        update_store(&json.store, |settings| {
+            settings.proxy.url = "http://proxy.example.com:8080".to_string();
+            settings.proxy.password = "hunter2-secret".to_string();
+            Ok(())
+        })
+        .unwrap();
+
+        let contents = std::fs::read_to_string(&json.path).unwrap();
+        assert!(!contents.contains("hunter2-secret"));
+        assert!(contents.contains(JSON_FIELD_PROTECTED_PASSWORD));
+        assert_eq!(json.store.load().unwrap().proxy.password, "hunter2-secret");
+    }
+
+    #[test]
+    fn json_store_loads_defaults_for_a_missing_file() {
+        let json = TempJsonStore::new("missing");
+
+        let (settings, issues) = json.store.load_with_issues().unwrap();
+
+        assert_eq!(settings, AppSettings::default());
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn json_store_loads_defaults_for_missing_values() {
+        let json = TempJsonStore::with_contents(
+            "partial",
+            r#"{ "schema_version": 3, "solar": { "auto_theme_enabled": true } }"#,
+        );
+        let defaults = AppSettings::default();
+
+        let settings = json.store.load().unwrap();
+
+        assert!(settings.solar.auto_theme_enabled);
+        assert_eq!(settings.solar.sunset_offset_minutes, 0);
+        assert_eq!(settings.solar.location, None);
+        assert_eq!(settings.language_preference, defaults.language_preference);
+        assert_eq!(settings.location_auto_update, defaults.location_auto_update);
+        assert_eq!(settings.saved_locations, defaults.saved_locations);
+    }
+
+    #[test]
+    fn json_store_reports_an_invalid_value_and_keeps_the_others() {
+        let json = TempJsonStore::with_contents(
+            "invalid",
+            r#"{ "language_preference": "de-DE", "solar": { "sunset_offset_minutes": "late", "auto_theme_enabled": true } }"#,
+        );
+
+        let (settings, issues) = json.store.load_with_issues().unwrap();
+
+        assert_eq!(settings.language_preference, "de-DE");
+        assert!(settings.solar.auto_theme_enabled);
+        assert_eq!(settings.solar.sunset_offset_minutes, 0);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "solar.sunset_offset_minutes");
+        assert_eq!(issues[0].error.code, "errors.settings.invalid_value");
+        assert_eq!(issues[0].error.params["value"], r#""late""#);
+    }
+
+    #[test]
+    fn json_store_reports_an_out_of_range_sunset_offset() {
+        let json = TempJsonStore::with_contents(
+            "sunset-offset",
+            r#"{ "solar": { "sunset_offset_minutes": 5000 } }"#,
+        );
+
+        let (settings, issues) = json.store.load_with_issues().unwrap();
+
+        assert_eq!(settings.solar.sunset_offset_minutes, 0);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "solar.sunset_offset_minutes");
+        assert_eq!(issues[0].error.params["value"], "5000");
+    }
+
+    #[test]
+    fn json_store_rejects_a_document_that_is_not_an_object() {
+        let json = TempJsonStore::with_contents("not-an-object", "[]");
+
+        let error = json.store.load_with_issues().unwrap_err();
+
+        assert_eq!(error.code, "errors.settings.load_failed");
+        assert!(update_store(&json.store, |_| Ok(())).is_err());
+    }
+}
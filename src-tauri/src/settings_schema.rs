@@ -0,0 +1,239 @@
+use crate::models::{
+    AppError, AppResult, AppSettings, AppliedSettingsMigration, SettingsIssue, SettingsReport,
+};
+use std::sync::OnceLock;
+
+/// Settings written before `SchemaVersion` existed.
+const LEGACY_SCHEMA_VERSION: u32 = 1;
+pub const SETTINGS_SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+const SETTINGS_LOG_FILE: &str = "settings.log";
+
+static STARTUP_REPORT: OnceLock<SettingsReport> = OnceLock::new();
+
+/// Moves settings from `version - 1` to `version`. `migrate` must leave settings that
+/// already have the new shape alone, and returns whether it changed anything.
+struct Migration {
+    version: u32,
+    name: &'static str,
+    migrate: fn(&mut AppSettings) -> bool,
+}
+
+/// In ascending `version` order.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        name: "record_solar_time_zone",
+        migrate: record_solar_time_zone,
+    },
+    Migration {
+        version: 3,
+        name: "canonicalize_language_preference",
+        migrate: canonicalize_language_preference,
+    },
+    Migration {
+        version: 4,
+        name: "protect_proxy_password",
+        migrate: protect_proxy_password,
+    },
+];
+
+pub fn legacy_schema_version() -> u32 {
+    LEGACY_SCHEMA_VERSION
+}
+
+/// Brings `settings` up to `SETTINGS_SCHEMA_VERSION`. Settings from a newer build are
+/// left as they are.
+pub fn migrate(settings: &mut AppSettings) -> Vec<AppliedSettingsMigration> {
+    let stored_version = settings.schema_version;
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > stored_version)
+    {
+        let changed = (migration.migrate)(settings);
+        settings.schema_version = migration.version;
+        applied.push(AppliedSettingsMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            changed,
+        });
+    }
+
+    applied
+}
+
+/// Migrates the stored settings and checks their values, logging both. Runs once at
+/// startup before anything else reads settings.
+pub fn run_startup_migrations() {
+    let previous_version = crate::settings::load()
+        .map(|settings| settings.schema_version)
+        .ok();
+    let report = migrate_stored_settings().unwrap_or_else(|error| SettingsReport {
+        error: Some(error),
+        ..SettingsReport::default()
+    });
+
+    write_log(previous_version, &report);
+    let _ = STARTUP_REPORT.set(report);
+}
+
+#[tauri::command]
+pub fn get_settings_report() -> AppResult<SettingsReport> {
+    Ok(STARTUP_REPORT.get().cloned().unwrap_or_default())
+}
+
+fn migrate_stored_settings() -> AppResult<SettingsReport> {
+    // Unreadable values are reported before the first save replaces them with defaults.
+    let (_, mut issues) = crate::settings::load_with_issues()?;
+
+    let mut migrations = Vec::new();
+    let settings = crate::settings::update(|settings| {
+        migrations = migrate(settings);
+        Ok(())
+    })?;
+    issues.extend(check_settings(&settings));
+
+    Ok(SettingsReport {
+        schema_version: settings.schema_version,
+        migrations,
+        issues,
+        error: None,
+    })
+}
+
+/// Runs each section through the checks its setter applies, so values that are out of
+/// range are reported instead of being clamped or zeroed where they're read.
+fn check_settings(settings: &AppSettings) -> Vec<SettingsIssue> {
+    let checks = [
+        (
+            "language_preference",
+            crate::i18n::normalize_language_preference(&settings.language_preference).map(drop),
+        ),
+        (
+            "solar",
+            crate::commands::validate_solar_settings(&settings.solar),
+        ),
+        (
+            "geocoder",
+            crate::geocoder::validate_geocoder_settings(&settings.geocoder),
+        ),
+        (
+            "ip_location",
+            crate::location_provider::validate_ip_location_settings(&settings.ip_location),
+        ),
+        (
+            "location_auto_update",
+            crate::location_provider::validate_location_auto_update_settings(
+                &settings.location_auto_update,
+            ),
+        ),
+        (
+            "proxy",
+            crate::proxy::validate_proxy_settings(&settings.proxy),
+        ),
+        (
+            "saved_locations",
+            crate::locations::validate_saved_locations(&settings.saved_locations),
+        ),
+    ];
+
+    checks
+        .into_iter()
+        .filter_map(|(field, result)| {
+            result.err().map(|error| SettingsIssue {
+                field: field.to_string(),
+                error,
+            })
+        })
+        .collect()
+}
+
+/// Locations saved before time zones were recorded are assumed to belong to the current one.
+fn record_solar_time_zone(settings: &mut AppSettings) -> bool {
+    if settings.solar.location.is_none() || !settings.solar.time_zone.is_empty() {
+        return false;
+    }
+
+    let Some(time_zone) = crate::timezone::system_windows_time_zone() else {
+        return false;
+    };
+    settings.solar.time_zone = time_zone;
+    true
+}
+
+/// Normalizes hand-edited preferences such as `english`. Unsupported ones are left for
+/// `check_settings` to report.
+fn canonicalize_language_preference(settings: &mut AppSettings) -> bool {
+    let Ok(canonical) = crate::i18n::normalize_language_preference(&settings.language_preference)
+    else {
+        return false;
+    };
+    if canonical == settings.language_preference {
+        return false;
+    }
+
+    settings.language_preference = canonical;
+    true
+}
+
+/// Nothing to change in memory: the version bump makes startup save the settings, and the
+/// store encrypts a password older builds kept in plain text.
+fn protect_proxy_password(settings: &mut AppSettings) -> bool {
+    !settings.proxy.password.is_empty()
+}
+
+/// Appends what happened to `settings.log` in the log directory. Quiet startups write
+/// nothing.
+fn write_log(previous_version: Option<u32>, report: &SettingsReport) {
+    let mut lines = Vec::new();
+    if let Some(previous_version) = previous_version {
+        if previous_version > SETTINGS_SCHEMA_VERSION {
+            lines.push(format!(
+                "settings schema {previous_version} is newer than {SETTINGS_SCHEMA_VERSION}; left unchanged"
+            ));
+        } else if previous_version != report.schema_version && report.error.is_none() {
+            lines.push(format!(
+                "settings schema {previous_version} -> {}",
+                report.schema_version
+            ));
+        }
+    }
+    for migration in &report.migrations {
+        let outcome = if migration.changed {
+            "applied"
+        } else {
+            "nothing to change"
+        };
+        lines.push(format!(
+            "migration {} {}: {outcome}",
+            migration.version, migration.name
+        ));
+    }
+    for issue in &report.issues {
+        lines.push(format!(
+            "invalid {}: {}",
+            issue.field,
+            describe_error(&issue.error)
+        ));
+    }
+    if let Some(error) = &report.error {
+        lines.push(format!("migration failed: {}", describe_error(error)));
+    }
+
+    crate::app_log::append(SETTINGS_LOG_FILE, &lines);
+}
+
+fn describe_error(error: &AppError) -> String {
+    let mut params: Vec<String> = error
+        .params
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    params.sort();
+
+    if params.is_empty() {
+        error.code.clone()
+    } else {
+        format!("{} ({})", error.code, params.join(", "))
+    }
+}
@@ -0,0 +1,326 @@
+use crate::models::{
+    AppError, AppResult, AppSettings, SettingsDocument, SettingsFieldChange, SettingsImportPreview,
+};
+use chrono::Local;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+/// Version written by this build; documents from newer builds are rejected rather than
+/// half-understood.
+const SETTINGS_DOCUMENT_VERSION: u32 = 1;
+const STARTUP_ENABLED_FIELD: &str = "startup_enabled";
+/// Kept from this machine on import, see `import_settings`.
+const LAST_CHECKED_FIELD: &str = "location_auto_update.last_checked_at";
+const ORIGINAL_THEME_FIELD: &str = "original_theme";
+const PROXY_PASSWORD_FIELD: &str = "proxy.password";
+/// Left out of exports; see `export_settings`.
+const DOCUMENT_PROXY_POINTER: &str = "/settings/proxy";
+const DOCUMENT_PASSWORD_KEY: &str = "password";
+const REDACTED_VALUE: &str = "********";
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn err_with_source(code: &str, source: impl ToString) -> AppError {
+    AppError::new(code).with_param("source", source.to_string())
+}
+
+/// Writes every persisted setting plus the Run-at-startup preference to `path`. The proxy
+/// password is left out so it never ends up in a plain-text file; importing keeps the
+/// password already set on the target machine.
+#[tauri::command]
+pub fn export_settings(path: String) -> AppResult<()> {
+    let document = SettingsDocument {
+        version: SETTINGS_DOCUMENT_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        settings: crate::settings::load()?,
+        startup_enabled: current_startup_enabled()?,
+    };
+
+    let write_failed =
+        |error: serde_json::Error| err_with_source("errors.settings_transfer.write_failed", error);
+    let mut value = serde_json::to_value(&document).map_err(write_failed)?;
+    if let Some(proxy) = value
+        .pointer_mut(DOCUMENT_PROXY_POINTER)
+        .and_then(Value::as_object_mut)
+    {
+        proxy.remove(DOCUMENT_PASSWORD_KEY);
+    }
+
+    let bytes = serde_json::to_vec_pretty(&value).map_err(write_failed)?;
+    write_file(Path::new(path.trim()), &bytes)
+}
+
+/// Validates the document at `path` and lists what importing it would change.
+#[tauri::command]
+pub fn preview_settings_import(path: String) -> AppResult<SettingsImportPreview> {
+    let document = read_document(Path::new(path.trim()))?;
+    build_preview(&document)
+}
+
+/// Replaces all settings with the document at `path` in a single save. Returns the
+/// differences that were applied.
+#[tauri::command]
+pub fn import_settings(app: AppHandle, path: String) -> AppResult<SettingsImportPreview> {
+    let document = read_document(Path::new(path.trim()))?;
+    let preview = build_preview(&document)?;
+    if preview.changes.is_empty() {
+        return Ok(preview);
+    }
+
+    // The Run key goes first: undoing it is a single value, while undoing a settings save
+    // would overwrite whatever else changed them in the meantime.
+    let startup_change = startup_change(&document)?;
+    if let Some(enabled) = startup_change {
+        crate::commands::set_startup_enabled_internal(enabled)?;
+    }
+
+    let previous_locale = crate::i18n::current_shared_locale();
+    let mut imported = document.settings.clone();
+    let saved = crate::settings::update(|settings| {
+        // When this machine last looked for a new location, and the theme it had before
+        // WinLux, are not something to carry over.
+        imported.location_auto_update.last_checked_at =
+            settings.location_auto_update.last_checked_at.clone();
+        imported.original_theme = settings.original_theme.clone();
+        *settings = imported;
+        Ok(())
+    });
+    if let Err(error) = saved {
+        if let Some(enabled) = startup_change {
+            let _ = crate::commands::set_startup_enabled_internal(!enabled);
+        }
+        return Err(error);
+    }
+
+    notify_settings_imported(&app, previous_locale)?;
+    Ok(preview)
+}
+
+fn read_document(path: &Path) -> AppResult<SettingsDocument> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| err_with_source("errors.settings_transfer.read_failed", error))?;
+    let value: Value = serde_json::from_slice(&bytes)
+        .map_err(|error| err_with_source("errors.settings_transfer.invalid_document", error))?;
+
+    // Checked before the full parse so a newer file reports its version instead of
+    // whichever field it renamed first.
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| err_with_source("errors.settings_transfer.invalid_document", "version"))?;
+    if version == 0 || version > u64::from(SETTINGS_DOCUMENT_VERSION) {
+        return Err(err("errors.settings_transfer.unsupported_version")
+            .with_param("version", version)
+            .with_param("max", SETTINGS_DOCUMENT_VERSION));
+    }
+
+    let has_proxy_password = value
+        .pointer(DOCUMENT_PROXY_POINTER)
+        .and_then(|proxy| proxy.get(DOCUMENT_PASSWORD_KEY))
+        .is_some();
+    let mut document: SettingsDocument = serde_json::from_value(value)
+        .map_err(|error| err_with_source("errors.settings_transfer.invalid_document", error))?;
+    if !has_proxy_password {
+        document.settings.proxy.password = crate::settings::load()?.proxy.password;
+    }
+    crate::settings_schema::migrate(&mut document.settings);
+    validate_settings(&mut document.settings)?;
+    Ok(document)
+}
+
+/// Applies every module's own checks to a document that bypassed the individual setters.
+fn validate_settings(settings: &mut AppSettings) -> AppResult<()> {
+    settings.language_preference =
+        crate::i18n::normalize_language_preference(&settings.language_preference)?;
+    crate::commands::validate_solar_settings(&settings.solar)?;
+    crate::geocoder::validate_geocoder_settings(&settings.geocoder)?;
+    crate::location_provider::validate_ip_location_settings(&settings.ip_location)?;
+    crate::location_provider::validate_location_auto_update_settings(
+        &settings.location_auto_update,
+    )?;
+    crate::proxy::validate_proxy_settings(&settings.proxy)?;
+    crate::locations::validate_saved_locations(&settings.saved_locations)?;
+
+    // The imported location now belongs to this machine's time zone, not the exporter's.
+    settings.solar.time_zone = match settings.solar.location {
+        Some(_) => crate::timezone::system_windows_time_zone().unwrap_or_default(),
+        None => String::new(),
+    };
+
+    Ok(())
+}
+
+fn build_preview(document: &SettingsDocument) -> AppResult<SettingsImportPreview> {
+    let current = flatten_settings(&crate::settings::load()?)?;
+    let imported = flatten_settings(&document.settings)?;
+
+    let mut changes: Vec<SettingsFieldChange> = changed_fields(&current, &imported)
+        .into_iter()
+        .filter(|(field, _, _)| {
+            field != LAST_CHECKED_FIELD && !field.starts_with(ORIGINAL_THEME_FIELD)
+        })
+        .map(|(field, current, imported)| redact_change(field, current, imported))
+        .collect();
+
+    if let Some(enabled) = startup_change(document)? {
+        changes.push(SettingsFieldChange {
+            field: STARTUP_ENABLED_FIELD.to_string(),
+            current: Value::Bool(!enabled),
+            imported: Value::Bool(enabled),
+        });
+    }
+
+    Ok(SettingsImportPreview {
+        version: document.version,
+        exported_at: document.exported_at.clone(),
+        changes,
+    })
+}
+
+/// Startup preference to apply, if the document has one that differs from this machine's.
+/// Portable copies never touch the Run key.
+fn startup_change(document: &SettingsDocument) -> AppResult<Option<bool>> {
+    let Some(imported) = document.startup_enabled else {
+        return Ok(None);
+    };
+
+    match current_startup_enabled()? {
+        Some(current) if current != imported => Ok(Some(imported)),
+        _ => Ok(None),
+    }
+}
+
+fn current_startup_enabled() -> AppResult<Option<bool>> {
+    let state = crate::commands::get_startup_state()?;
+    Ok((!state.portable).then_some(state.enabled))
+}
+
+fn flatten_settings(settings: &AppSettings) -> AppResult<BTreeMap<String, Value>> {
+    let value = serde_json::to_value(settings)
+        .map_err(|error| err_with_source("errors.settings_transfer.invalid_document", error))?;
+
+    Ok(flatten_fields(value))
+}
+
+/// Maps a serialized settings value to dotted field paths. Arrays, such as the saved
+/// locations, are compared as a whole.
+pub(crate) fn flatten_fields(value: Value) -> BTreeMap<String, Value> {
+    let mut fields = BTreeMap::new();
+    flatten_value(String::new(), value, &mut fields);
+    fields
+}
+
+/// `(field, before, after)` for every field whose value differs. A location that is set
+/// on one side only shows up as fields on that side alone, compared against `null`.
+pub(crate) fn changed_fields(
+    before: &BTreeMap<String, Value>,
+    after: &BTreeMap<String, Value>,
+) -> Vec<(String, Value, Value)> {
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let value = |fields: &BTreeMap<String, Value>, field: &str| {
+        fields.get(field).cloned().unwrap_or(Value::Null)
+    };
+
+    fields
+        .into_iter()
+        .map(|field| (field.clone(), value(before, field), value(after, field)))
+        .filter(|(_, before, after)| before != after)
+        .collect()
+}
+
+fn flatten_value(prefix: String, value: Value, fields: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let field = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_value(field, value, fields);
+            }
+        }
+        value => {
+            fields.insert(prefix, value);
+        }
+    }
+}
+
+fn redact_change(field: String, current: Value, imported: Value) -> SettingsFieldChange {
+    let redact = |value: Value| match value {
+        Value::String(text) if field == PROXY_PASSWORD_FIELD && !text.is_empty() => {
+            Value::String(REDACTED_VALUE.to_string())
+        }
+        value => value,
+    };
+
+    SettingsFieldChange {
+        current: redact(current),
+        imported: redact(imported),
+        field,
+    }
+}
+
+fn notify_settings_imported(app: &AppHandle, previous_locale: &str) -> AppResult<()> {
+    crate::http::reset_shared_client();
+
+    if crate::i18n::current_shared_locale() != previous_locale {
+        crate::locations::spawn_location_relocalization(app.clone());
+    }
+    let _ = app.emit(
+        crate::i18n::LANGUAGE_CHANGED_EVENT,
+        &crate::i18n::get_language_settings(),
+    );
+    crate::tray::refresh_tray_language()
+        .map_err(|error| err_with_source("errors.tray.refresh_language_failed", error))?;
+
+    let _ = app.emit(
+        crate::commands::STARTUP_STATE_CHANGED_EVENT,
+        &crate::commands::get_startup_state()?,
+    );
+    let _ = app.emit(
+        crate::locations::SAVED_LOCATIONS_CHANGED_EVENT,
+        &crate::locations::get_saved_locations()?,
+    );
+    let _ = app.emit(
+        crate::geocoder::GEOCODER_SETTINGS_CHANGED_EVENT,
+        &crate::geocoder::get_geocoder_settings()?,
+    );
+    let _ = app.emit(
+        crate::location_provider::IP_LOCATION_SETTINGS_CHANGED_EVENT,
+        &crate::location_provider::get_ip_location_settings()?,
+    );
+    let _ = app.emit(
+        crate::location_provider::LOCATION_AUTO_UPDATE_SETTINGS_CHANGED_EVENT,
+        &crate::location_provider::get_location_auto_update_settings()?,
+    );
+    let _ = app.emit(
+        crate::proxy::PROXY_SETTINGS_CHANGED_EVENT,
+        &crate::proxy::get_proxy_settings()?,
+    );
+    crate::location_provider::notify_location_auto_update_worker();
+    crate::network::reevaluate_network_location();
+
+    // Emits `SOLAR_SETTINGS_CHANGED_EVENT` and re-applies the theme for the new location.
+    crate::commands::finish_solar_location_change(app)?;
+    Ok(())
+}
+
+/// Writes through a sibling temporary file so a failed export never leaves a truncated
+/// document behind.
+fn write_file(path: &Path, bytes: &[u8]) -> AppResult<()> {
+    let write_failed =
+        |error: std::io::Error| err_with_source("errors.settings_transfer.write_failed", error);
+
+    let mut temp_path = OsString::from(path.as_os_str());
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    std::fs::write(&temp_path, bytes).map_err(write_failed)?;
+    std::fs::rename(&temp_path, path).map_err(write_failed)
+}
@@ -0,0 +1,184 @@
+use crate::commands::{
+    auto_theme_wait_duration, record_auto_theme_history, run_auto_theme_step, StepMode,
+};
+use crate::models::{
+    AppError, AppResult, AutoThemeSimulationStatus, GeocodeResult, ThemeMode, ThemeState,
+};
+use crate::theme_backend::InMemoryThemeBackend;
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+pub const AUTO_THEME_SIMULATION_PROGRESS_EVENT: &str = "auto-theme-simulation-progress";
+pub const AUTO_THEME_SIMULATION_FINISHED_EVENT: &str = "auto-theme-simulation-finished";
+const SIMULATION_DAYS_MAX: u32 = 366;
+const SIMULATION_SPEED_MAX: u32 = 7 * 24 * 60 * 60;
+
+static SIMULATION_RUN_ID: AtomicU64 = AtomicU64::new(0);
+static SIMULATION_CANCEL_SIGNAL: OnceLock<Notify> = OnceLock::new();
+
+fn err(code: &str) -> AppError {
+    AppError::new(code)
+}
+
+fn simulation_cancel_signal() -> &'static Notify {
+    SIMULATION_CANCEL_SIGNAL.get_or_init(Notify::new)
+}
+
+/// Ends the current run, waking it if it is sleeping until its next step, and returns the
+/// id of the next run.
+fn cancel_simulation() -> u64 {
+    let run_id = SIMULATION_RUN_ID.fetch_add(1, Ordering::SeqCst) + 1;
+    simulation_cancel_signal().notify_waiters();
+    run_id
+}
+
+fn format_local(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S %:z").to_string()
+}
+
+fn validate_simulation_request(days: u32, speed: u32) -> AppResult<()> {
+    if days == 0 || days > SIMULATION_DAYS_MAX {
+        return Err(err("errors.simulation.invalid_days")
+            .with_param("min", 1)
+            .with_param("max", SIMULATION_DAYS_MAX)
+            .with_param("value", days));
+    }
+
+    if speed == 0 || speed > SIMULATION_SPEED_MAX {
+        return Err(err("errors.simulation.invalid_speed")
+            .with_param("min", 1)
+            .with_param("max", SIMULATION_SPEED_MAX)
+            .with_param("value", speed));
+    }
+
+    Ok(())
+}
+
+/// Replays the auto-theme scheduler over `days` of simulated time, where `speed` is the
+/// number of simulated seconds per real second (3600 = one hour per second).
+#[tauri::command]
+pub fn start_auto_theme_simulation(
+    app: AppHandle,
+    days: u32,
+    speed: u32,
+) -> AppResult<AutoThemeSimulationStatus> {
+    validate_simulation_request(days, speed)?;
+
+    let settings = crate::commands::get_solar_settings()?;
+    let location = settings
+        .effective_location()
+        .cloned()
+        .ok_or_else(|| err("errors.simulation.location_required"))?;
+    let initial_state = crate::commands::get_theme_state().unwrap_or(ThemeState {
+        apps: ThemeMode::Light,
+        system: ThemeMode::Light,
+    });
+
+    let run_id = cancel_simulation();
+    let simulated_start = Local::now();
+    let simulated_end = simulated_start + ChronoDuration::days(i64::from(days));
+    let status = AutoThemeSimulationStatus {
+        run_id,
+        running: true,
+        days,
+        speed,
+        simulated_start: format_local(simulated_start),
+        simulated_end: format_local(simulated_end),
+        simulated_now: format_local(simulated_start),
+        theme: initial_state.apps,
+        switch_count: 0,
+        error: None,
+    };
+
+    let run_status = status.clone();
+    tauri::async_runtime::spawn(async move {
+        run_simulation(
+            app,
+            run_status,
+            location,
+            settings.sunset_offset_minutes,
+            initial_state,
+            simulated_start,
+            simulated_end,
+        )
+        .await;
+    });
+
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_auto_theme_simulation() -> AppResult<()> {
+    cancel_simulation();
+    Ok(())
+}
+
+async fn run_simulation(
+    app: AppHandle,
+    mut status: AutoThemeSimulationStatus,
+    location: GeocodeResult,
+    sunset_offset_minutes: i64,
+    initial_state: ThemeState,
+    simulated_start: DateTime<Local>,
+    simulated_end: DateTime<Local>,
+) {
+    let mut backend = InMemoryThemeBackend::new(initial_state);
+    let mut simulated_now = simulated_start;
+
+    loop {
+        // Registered before the run id check, so a stop in between still ends the sleep.
+        let mut cancelled = std::pin::pin!(simulation_cancel_signal().notified());
+        cancelled.as_mut().enable();
+        if SIMULATION_RUN_ID.load(Ordering::SeqCst) != status.run_id {
+            break;
+        }
+
+        let step = match run_auto_theme_step(
+            &mut backend,
+            location.clone(),
+            sunset_offset_minutes,
+            simulated_now,
+            StepMode::Simulated,
+        ) {
+            Ok(step) => step,
+            Err(error) => {
+                status.error = Some(error);
+                break;
+            }
+        };
+
+        status.simulated_now = format_local(simulated_now);
+        status.theme = backend.state().apps;
+
+        if let Some(entry) = step.applied {
+            status.switch_count += 1;
+            record_auto_theme_history(&app, entry);
+        }
+
+        let _ = app.emit(AUTO_THEME_SIMULATION_PROGRESS_EVENT, &status);
+
+        let wait = auto_theme_wait_duration(step.sun_times.seconds_until_next_transition);
+        let Ok(simulated_wait) = ChronoDuration::from_std(wait) else {
+            break;
+        };
+        let next_simulated_now = simulated_now + simulated_wait;
+        if next_simulated_now >= simulated_end {
+            break;
+        }
+
+        let real_wait = Duration::from_secs_f64(wait.as_secs_f64() / f64::from(status.speed));
+        tokio::select! {
+            _ = sleep(real_wait) => {}
+            _ = &mut cancelled => break,
+        }
+        simulated_now = next_simulated_now;
+    }
+
+    status.running = false;
+    let _ = app.emit(AUTO_THEME_SIMULATION_FINISHED_EVENT, &status);
+}
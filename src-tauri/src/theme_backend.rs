@@ -0,0 +1,55 @@
+use crate::models::{AppResult, ThemeState};
+use tauri::AppHandle;
+
+/// Target of the theme changes decided by the auto-theme scheduler.
+pub trait ThemeBackend {
+    fn current_state(&self) -> AppResult<ThemeState>;
+    fn apply_state(&mut self, state: ThemeState) -> AppResult<ThemeState>;
+}
+
+/// Writes to the Personalize registry key and notifies the window, like a manual switch.
+pub struct RegistryThemeBackend<'a> {
+    app: &'a AppHandle,
+}
+
+impl<'a> RegistryThemeBackend<'a> {
+    pub fn new(app: &'a AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl ThemeBackend for RegistryThemeBackend<'_> {
+    fn current_state(&self) -> AppResult<ThemeState> {
+        crate::commands::get_theme_state()
+    }
+
+    fn apply_state(&mut self, state: ThemeState) -> AppResult<ThemeState> {
+        crate::commands::apply_theme_state_for_app(self.app, state)
+    }
+}
+
+/// Keeps the theme in memory only; used when the scheduler must not touch the system.
+pub struct InMemoryThemeBackend {
+    state: ThemeState,
+}
+
+impl InMemoryThemeBackend {
+    pub fn new(state: ThemeState) -> Self {
+        Self { state }
+    }
+
+    pub fn state(&self) -> &ThemeState {
+        &self.state
+    }
+}
+
+impl ThemeBackend for InMemoryThemeBackend {
+    fn current_state(&self) -> AppResult<ThemeState> {
+        Ok(self.state.clone())
+    }
+
+    fn apply_state(&mut self, state: ThemeState) -> AppResult<ThemeState> {
+        self.state = state;
+        Ok(self.state.clone())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::models::{AppResult, GeocodeResult, LocationSource, SolarSettings, TimeZoneChange};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::time::sleep;
+
+/// Tab-separated Windows time zone ID and the IANA zones it covers, following CLDR's
+/// windowsZones mapping with current canonical IANA names. The first IANA zone is the
+/// territory 001 default.
+const WINDOWS_ZONES_DATA: &str = include_str!("../data/windows-zones.tsv");
+const TIME_ZONE_INFORMATION_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\TimeZoneInformation";
+const TIME_ZONE_VALUE_KEY_NAME: &str = "TimeZoneKeyName";
+pub const TIME_ZONE_CHANGED_EVENT: &str = "time-zone-changed";
+const TIME_ZONE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+static WINDOWS_ZONES: OnceLock<Vec<(&'static str, Vec<&'static str>)>> = OnceLock::new();
+static TIME_ZONE_WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+
+/// The Windows time zone ID of the system, e.g. `W. Europe Standard Time`.
+pub fn system_windows_time_zone() -> Option<String> {
+    use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ};
+    use winreg::RegKey;
+
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let key = hklm
+        .open_subkey_with_flags(TIME_ZONE_INFORMATION_KEY, KEY_READ)
+        .ok()?;
+    let raw: String = key.get_value(TIME_ZONE_VALUE_KEY_NAME).ok()?;
+    // Older Windows builds leave garbage after the terminating NUL.
+    let name = raw.split('\0').next().unwrap_or_default().trim();
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn windows_zones() -> &'static [(&'static str, Vec<&'static str>)] {
+    WINDOWS_ZONES.get_or_init(|| {
+        WINDOWS_ZONES_DATA
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(windows_id, iana)| (windows_id.trim(), iana.split_whitespace().collect()))
+            .collect()
+    })
+}
+
+pub fn windows_to_iana(windows_id: &str) -> Option<&'static str> {
+    windows_zones()
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(windows_id.trim()))
+        .and_then(|(_, iana)| iana.first().copied())
+}
+
+pub fn iana_to_windows(iana: &str) -> Option<&'static str> {
+    windows_zones()
+        .iter()
+        .find(|(_, zones)| zones.iter().any(|zone| zone.eq_ignore_ascii_case(iana)))
+        .map(|(id, _)| *id)
+}
+
+/// The Windows time zone a point most likely observes, taken from the nearest bundled
+/// city.
+pub fn windows_time_zone_at(latitude: f64, longitude: f64) -> Option<&'static str> {
+    let city = crate::offline_geocoder::nearest(latitude, longitude, f64::INFINITY)?;
+    iana_to_windows(&city.time_zone)
+}
+
+/// The system time zone as an IANA identifier, e.g. `Europe/Berlin`.
+pub fn system_time_zone() -> Option<&'static str> {
+    windows_to_iana(&system_windows_time_zone()?)
+}
+
+/// Stand-in for a missing solar location: the largest bundled city in the system time
+/// zone. Sunrise and sunset there are usually within half an hour of the user's own.
+pub fn provisional_location() -> Option<GeocodeResult> {
+    let time_zone = system_time_zone()?;
+    let city = crate::offline_geocoder::largest_city_in_time_zone(time_zone)?;
+
+    Some(GeocodeResult {
+        address: time_zone.to_string(),
+        display_name: city.display_name(),
+        latitude: city.latitude,
+        longitude: city.longitude,
+        source: LocationSource::TimeZone,
+    })
+}
+
+/// Compares the system time zone with the one recorded when the solar location was
+/// saved. `None` when they agree or either is unknown.
+pub fn current_time_zone_change() -> Option<TimeZoneChange> {
+    let previous_time_zone = crate::commands::solar_location_time_zone()?;
+    let current_time_zone = system_windows_time_zone()?;
+    if previous_time_zone.eq_ignore_ascii_case(&current_time_zone) {
+        return None;
+    }
+
+    let matching_location_ids = crate::locations::get_saved_locations()
+        .map(|saved_locations| saved_locations.locations)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|saved| {
+            windows_time_zone_at(saved.location.latitude, saved.location.longitude)
+                .is_some_and(|zone| zone.eq_ignore_ascii_case(&current_time_zone))
+        })
+        .map(|saved| saved.id)
+        .collect();
+
+    Some(TimeZoneChange {
+        previous_time_zone,
+        current_iana_time_zone: windows_to_iana(&current_time_zone).map(str::to_string),
+        current_time_zone,
+        matching_location_ids,
+        provisional_location: provisional_location(),
+    })
+}
+
+/// Watches for system time zone changes. A mismatch with the solar location's zone is
+/// reported once at startup and again whenever the zone changes; until the user switches
+/// location or dismisses it, `get_solar_settings` reports the provisional location.
+pub fn start_time_zone_watcher(app: AppHandle) {
+    if TIME_ZONE_WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_time_zone = system_windows_time_zone();
+        if let Some(change) = current_time_zone_change() {
+            let _ = app.emit(TIME_ZONE_CHANGED_EVENT, &change);
+        }
+
+        loop {
+            sleep(TIME_ZONE_POLL_INTERVAL).await;
+
+            let time_zone = system_windows_time_zone();
+            if time_zone == last_time_zone {
+                continue;
+            }
+            last_time_zone = time_zone;
+
+            if let Some(change) = current_time_zone_change() {
+                let _ = app.emit(TIME_ZONE_CHANGED_EVENT, &change);
+            }
+            let _ = crate::commands::finish_solar_location_change(&app);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_time_zone_change() -> AppResult<Option<TimeZoneChange>> {
+    Ok(current_time_zone_change())
+}
+
+/// Keeps the saved solar location after a time zone change.
+#[tauri::command]
+pub fn dismiss_time_zone_change(app: AppHandle) -> AppResult<SolarSettings> {
+    crate::commands::record_solar_location_time_zone()?;
+    crate::commands::finish_solar_location_change(&app)
+}
@@ -1,5 +1,5 @@
 use crate::i18n;
-use crate::models::{AppError, ThemeMode, ThemeState};
+use crate::models::{AppError, SavedLocations, ThemeMode, ThemeState};
 use std::sync::{Mutex, OnceLock};
 use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::TrayIconBuilder;
@@ -17,6 +17,9 @@ const MENU_SUNSET_OFFSET_5: &str = "tray_sunset_offset_5";
 const MENU_SUNSET_OFFSET_10: &str = "tray_sunset_offset_10";
 const MENU_SUNSET_OFFSET_15: &str = "tray_sunset_offset_15";
 const MENU_SUNSET_OFFSET_CUSTOM: &str = "tray_sunset_offset_custom";
+const MENU_LOCATION_MENU: &str = "tray_locations_menu";
+const MENU_LOCATION_EMPTY: &str = "tray_locations_empty";
+const MENU_LOCATION_PREFIX: &str = "tray_location_";
 const MENU_STARTUP: &str = "tray_startup";
 const MENU_LANGUAGE_AUTO: &str = "tray_language_auto";
 const MENU_LANGUAGE_PREFIX: &str = "tray_language_";
@@ -33,6 +36,9 @@ struct TrayMenuHandles {
     sunset_offset_10: CheckMenuItem<Wry>,
     sunset_offset_15: CheckMenuItem<Wry>,
     sunset_offset_custom: MenuItem<Wry>,
+    location_menu: Submenu<Wry>,
+    location_empty: MenuItem<Wry>,
+    location_items: Vec<(String, CheckMenuItem<Wry>)>,
     startup: CheckMenuItem<Wry>,
     language_menu: Submenu<Wry>,
     language_auto: CheckMenuItem<Wry>,
@@ -92,7 +98,7 @@ fn refresh_auto_theme_menu_item() {
             return;
         };
 
-        let is_configured = solar_settings.location.is_some();
+        let is_configured = solar_settings.effective_location().is_some();
         let label = i18n::tray_auto_theme_label(
             &current_language,
             is_configured,
@@ -156,6 +162,68 @@ fn refresh_sunset_offset_menu_item() {
     }
 }
 
+fn build_location_menu_items(
+    app: &AppHandle,
+    saved_locations: &SavedLocations,
+) -> Result<Vec<(String, CheckMenuItem<Wry>)>> {
+    let mut location_items = Vec::with_capacity(saved_locations.locations.len());
+    for saved in &saved_locations.locations {
+        let menu_id = format!("{MENU_LOCATION_PREFIX}{}", saved.id);
+        let is_active = saved_locations.active_id.as_deref() == Some(saved.id.as_str());
+        let location_item = CheckMenuItem::with_id(
+            app,
+            menu_id,
+            &saved.name,
+            true,
+            is_active,
+            None::<&str>,
+        )?;
+        location_items.push((saved.id.clone(), location_item));
+    }
+
+    Ok(location_items)
+}
+
+fn refresh_location_menu_items(app: &AppHandle) {
+    let Ok(saved_locations) = crate::locations::get_saved_locations() else {
+        return;
+    };
+    let language_settings = i18n::get_language_settings();
+    let current_language = language_settings.resolved;
+
+    let Ok(mut handles_guard) = tray_menu_handles().lock() else {
+        return;
+    };
+
+    let Some(handles) = handles_guard.as_mut() else {
+        return;
+    };
+
+    let _ = handles
+        .location_menu
+        .set_text(&i18n::tray_locations_menu_label(&current_language));
+    let _ = handles
+        .location_empty
+        .set_text(&i18n::tray_locations_empty_label(&current_language));
+
+    for (_, item) in handles.location_items.drain(..) {
+        let _ = handles.location_menu.remove(&item);
+    }
+    let _ = handles.location_menu.remove(&handles.location_empty);
+
+    let Ok(location_items) = build_location_menu_items(app, &saved_locations) else {
+        return;
+    };
+
+    if location_items.is_empty() {
+        let _ = handles.location_menu.append(&handles.location_empty);
+    }
+    for (_, item) in &location_items {
+        let _ = handles.location_menu.append(item);
+    }
+    handles.location_items = location_items;
+}
+
 fn refresh_startup_menu_item() {
     let Ok(startup_state) = crate::commands::get_startup_state() else {
         return;
@@ -167,7 +235,7 @@ fn refresh_startup_menu_item() {
 
     if let Some(handles) = handles_guard.as_ref() {
         let _ = handles.startup.set_checked(startup_state.enabled);
-        let _ = handles.startup.set_enabled(true);
+        let _ = handles.startup.set_enabled(!startup_state.portable);
     }
 }
 
@@ -186,7 +254,7 @@ pub fn refresh_tray_language() -> Result<()> {
         handles.theme_dark.set_text(&texts.dark_mode)?;
         handles.theme_light.set_text(&texts.light_mode)?;
         if let Ok(solar_settings) = crate::commands::get_solar_settings() {
-            let is_configured = solar_settings.location.is_some();
+            let is_configured = solar_settings.effective_location().is_some();
             handles.auto_theme.set_text(&i18n::tray_auto_theme_label(
                 &current_language,
                 is_configured,
@@ -220,6 +288,12 @@ pub fn refresh_tray_language() -> Result<()> {
                     solar_settings.sunset_offset_minutes,
                 ))?;
         }
+        handles
+            .location_menu
+            .set_text(&i18n::tray_locations_menu_label(&current_language))?;
+        handles
+            .location_empty
+            .set_text(&i18n::tray_locations_empty_label(&current_language))?;
         handles
             .startup
             .set_text(&i18n::tray_startup_label(&current_language))?;
@@ -264,8 +338,11 @@ fn build_tray_menu(app: &AppHandle, allow_open_main: bool) -> Result<(Menu<Wry>,
     let dark_selected = current_state.apps == ThemeMode::Dark;
     let solar_settings = crate::commands::get_solar_settings().unwrap_or(crate::models::SolarSettings {
         location: None,
+        provisional_location: None,
         auto_theme_enabled: false,
         sunset_offset_minutes: 0,
+        dry_run_enabled: false,
+        prefer_offline_geocoding: false,
     });
 
     let theme_dark = CheckMenuItem::with_id(
@@ -290,7 +367,7 @@ fn build_tray_menu(app: &AppHandle, allow_open_main: bool) -> Result<(Menu<Wry>,
         MENU_AUTO_THEME,
         &i18n::tray_auto_theme_label(
             &current_language,
-            solar_settings.location.is_some(),
+            solar_settings.effective_location().is_some(),
             solar_settings.auto_theme_enabled,
         ),
         true,
@@ -347,13 +424,44 @@ fn build_tray_menu(app: &AppHandle, allow_open_main: bool) -> Result<(Menu<Wry>,
         &sunset_offset_items,
     )?;
 
-    let startup_state = crate::commands::get_startup_state()
-        .unwrap_or(crate::models::StartupState { enabled: false });
+    let saved_locations = crate::locations::get_saved_locations().unwrap_or(SavedLocations {
+        locations: Vec::new(),
+        active_id: None,
+    });
+    let location_empty = MenuItem::with_id(
+        app,
+        MENU_LOCATION_EMPTY,
+        &i18n::tray_locations_empty_label(&current_language),
+        false,
+        None::<&str>,
+    )?;
+    let location_items = build_location_menu_items(app, &saved_locations)?;
+    let mut location_item_refs: Vec<&dyn tauri::menu::IsMenuItem<Wry>> =
+        Vec::with_capacity(location_items.len().max(1));
+    if location_items.is_empty() {
+        location_item_refs.push(&location_empty);
+    }
+    for (_, item) in &location_items {
+        location_item_refs.push(item);
+    }
+    let location_menu = Submenu::with_id_and_items(
+        app,
+        MENU_LOCATION_MENU,
+        &i18n::tray_locations_menu_label(&current_language),
+        true,
+        &location_item_refs,
+    )?;
+
+    let startup_state =
+        crate::commands::get_startup_state().unwrap_or(crate::models::StartupState {
+            enabled: false,
+            portable: crate::paths::is_portable(),
+        });
     let startup = CheckMenuItem::with_id(
         app,
         MENU_STARTUP,
         &i18n::tray_startup_label(&current_language),
-        true,
+        !startup_state.portable,
         startup_state.enabled,
         None::<&str>,
     )?;
@@ -407,6 +515,7 @@ fn build_tray_menu(app: &AppHandle, allow_open_main: bool) -> Result<(Menu<Wry>,
             &theme_light,
             &auto_theme,
             &sunset_offset_menu,
+            &location_menu,
             &startup,
             &language_menu,
             &separator_bottom,
@@ -424,6 +533,9 @@ fn build_tray_menu(app: &AppHandle, allow_open_main: bool) -> Result<(Menu<Wry>,
         sunset_offset_10,
         sunset_offset_15,
         sunset_offset_custom,
+        location_menu,
+        location_empty,
+        location_items,
         startup,
         language_menu,
         language_auto,
@@ -454,6 +566,20 @@ pub fn setup_tray(app: &AppHandle, allow_open_main: bool) -> Result<()> {
         refresh_startup_menu_item();
     });
 
+    let settings_app = app.clone();
+    app.listen_any(crate::app_settings::SETTINGS_CHANGED_EVENT, move |_| {
+        refresh_auto_theme_menu_item();
+        refresh_sunset_offset_menu_item();
+        refresh_startup_menu_item();
+        refresh_location_menu_items(&settings_app);
+        let _ = refresh_tray_language();
+    });
+
+    let location_app = app.clone();
+    app.listen_any(crate::locations::SAVED_LOCATIONS_CHANGED_EVENT, move |_| {
+        refresh_location_menu_items(&location_app);
+    });
+
     // Reuse the window icon as tray icon (works as long as we have an icon embedded).
     let icon = app.default_window_icon().cloned();
 
@@ -499,7 +625,7 @@ pub fn setup_tray(app: &AppHandle, allow_open_main: bool) -> Result<()> {
                         return;
                     };
 
-                    if settings.location.is_none() {
+                    if settings.effective_location().is_none() {
                         if allow_open_main {
                             open_main_window(app);
                         }
@@ -555,6 +681,12 @@ pub fn setup_tray(app: &AppHandle, allow_open_main: bool) -> Result<()> {
                     );
                 }
                 _ => {
+                    if let Some(location_id) = menu_id.strip_prefix(MENU_LOCATION_PREFIX) {
+                        let _ = crate::locations::activate_saved_location_for_app(app, location_id);
+                        refresh_location_menu_items(app);
+                        return;
+                    }
+
                     if let Some(language) = menu_id.strip_prefix(MENU_LANGUAGE_PREFIX) {
                         let _ = crate::commands::set_language_preference_for_app(app, language);
                     }
@@ -580,6 +712,7 @@ pub fn setup_tray(app: &AppHandle, allow_open_main: bool) -> Result<()> {
                 refresh_theme_menu_items();
                 refresh_auto_theme_menu_item();
                 refresh_sunset_offset_menu_item();
+                refresh_location_menu_items(&tray.app_handle());
                 refresh_startup_menu_item();
                 let _ = refresh_tray_language();
             }
@@ -108,6 +108,7 @@ function App() {
   const [todaySunTimesLoading, setTodaySunTimesLoading] = useState(false)
   const [startupLoading, setStartupLoading] = useState(false)
   const [startupEnabled, setStartupEnabledLocal] = useState(false)
+  const [startupPortable, setStartupPortable] = useState(false)
   const currentLanguage = languageSettings?.resolved ?? 'English'
 
   const registryPath =
@@ -177,6 +178,7 @@ function App() {
 
     void listen<StartupState>(STARTUP_STATE_CHANGED_EVENT, (event) => {
       setStartupEnabledLocal(event.payload.enabled)
+      setStartupPortable(event.payload.portable)
       setThemeError(null)
       setStartupLoading(false)
     }).then((fn) => {
@@ -291,6 +293,7 @@ function App() {
     try {
       const state = await getStartupState()
       setStartupEnabledLocal(state.enabled)
+      setStartupPortable(state.portable)
     } catch (error) {
       setThemeError(toErrorMessage(error, currentLanguage))
     } finally {
@@ -378,6 +381,7 @@ function App() {
         display_name: result.display_name,
         latitude: result.latitude,
         longitude: result.longitude,
+        source: 'geocoded',
       })
     } catch (error) {
       setSolarError(toErrorMessage(error, currentLanguage))
@@ -397,6 +401,7 @@ function App() {
         display_name: result.display_name,
         latitude: result.latitude,
         longitude: result.longitude,
+        source: solarSettings?.location?.source ?? 'geocoded',
       })
     } catch (error) {
       setSolarError(toErrorMessage(error, currentLanguage))
@@ -466,6 +471,7 @@ function App() {
     try {
       const state = await setStartupEnabled(enabled)
       setStartupEnabledLocal(state.enabled)
+      setStartupPortable(state.portable)
     } catch (error) {
       setThemeError(toErrorMessage(error, currentLanguage))
     } finally {
@@ -772,7 +778,7 @@ function App() {
               <button
                 type="button"
                 className={`btn ${startupEnabled ? 'btnPrimary' : 'btnSecondary'}`}
-                disabled={startupLoading}
+                disabled={startupLoading || startupPortable}
                 onClick={() => {
                   void toggleStartup(true)
                 }}
@@ -782,7 +788,7 @@ function App() {
               <button
                 type="button"
                 className={`btn ${startupEnabled ? 'btnSecondary' : 'btnPrimary'}`}
-                disabled={startupLoading}
+                disabled={startupLoading || startupPortable}
                 onClick={() => {
                   void toggleStartup(false)
                 }}
@@ -808,6 +814,9 @@ function App() {
               {startupEnabled ? startupEnabledText : startupDisabledText}
             </code>
           </div>
+          {startupPortable && (
+            <p className="hint">{translate(currentLanguage, 'startup.portable_hint')}</p>
+          )}
           <p className="hint">
             {translate(currentLanguage, 'info.osm_copyright_prefix')}
             <a
@@ -16,21 +16,216 @@ export interface AppErrorPayload {
   params?: Record<string, string>
 }
 
+export type LocationSource = 'geocoded' | 'manual' | 'offline' | 'ip_approximate' | 'time_zone' | 'device'
+
+export type GeocoderProvider = 'nominatim' | 'nominatim_self_hosted' | 'photon'
+
+export interface GeocoderSettings {
+  provider: GeocoderProvider
+  endpoint: string
+  default_endpoint: string
+}
+
+export interface IpLocationSettings {
+  enabled: boolean
+  endpoint: string
+  default_endpoint: string
+}
+
+export interface LocationAutoUpdateSettings {
+  enabled: boolean
+  interval_hours: number
+  min_distance_km: number
+  last_checked_at: string | null
+}
+
+export interface TimeZoneChange {
+  previous_time_zone: string
+  current_time_zone: string
+  current_iana_time_zone: string | null
+  matching_location_ids: string[]
+  provisional_location: GeocodeResult | null
+}
+
+export type ProxySource = 'manual' | 'environment' | 'system' | 'direct'
+
+export interface ProxySettings {
+  url: string
+  username: string
+  has_password: boolean
+}
+
+export interface ConnectionTestResult {
+  proxy_source: ProxySource
+  proxy_url: string | null
+  endpoint: string
+  status: number
+  elapsed_ms: number
+}
+
 export interface GeocodeResult {
   address: string
   display_name: string
   latitude: number
   longitude: number
+  source: LocationSource
+}
+
+export interface GeocodeBoundingBox {
+  south: number
+  north: number
+  west: number
+  east: number
+}
+
+export interface LocationCandidate {
+  display_name: string
+  place_type: string
+  country_code: string | null
+  bounding_box: GeocodeBoundingBox | null
+  latitude: number
+  longitude: number
+  source: LocationSource
 }
 
 export interface SolarSettings {
   location: GeocodeResult | null
+  provisional_location: GeocodeResult | null
   auto_theme_enabled: boolean
   sunset_offset_minutes: number
+  dry_run_enabled: boolean
+  prefer_offline_geocoding: boolean
+}
+
+export type NetworkRuleKind = 'wifi_ssid' | 'dns_suffix'
+
+export interface NetworkRule {
+  kind: NetworkRuleKind
+  value: string
+}
+
+export interface SavedLocation {
+  id: string
+  name: string
+  location: GeocodeResult
+  network_rules: NetworkRule[]
+}
+
+export interface NetworkIdentifiers {
+  wifi_ssids: string[]
+  dns_suffixes: string[]
+}
+
+export type NetworkLocationReason =
+  | 'no_rules'
+  | 'no_match'
+  | 'matched'
+  | 'already_active'
+  | 'ambiguous'
+  | 'network_unavailable'
+  | 'activation_failed'
+
+export interface NetworkLocationStatus {
+  identifiers: NetworkIdentifiers
+  reason: NetworkLocationReason
+  location_ids: string[]
+  matched_rule: NetworkRule | null
+  activated: boolean
+  checked_at: string
+  error: AppErrorPayload | null
+}
+
+export interface SavedLocations {
+  locations: SavedLocation[]
+  active_id: string | null
+}
+
+export interface SettingsIssue {
+  field: string
+  error: AppErrorPayload
+}
+
+export interface AppliedSettingsMigration {
+  version: number
+  name: string
+  changed: boolean
+}
+
+export interface SettingsReport {
+  schema_version: number
+  migrations: AppliedSettingsMigration[]
+  issues: SettingsIssue[]
+  error: AppErrorPayload | null
+}
+
+export interface SettingsFieldChange {
+  field: string
+  current: unknown
+  imported: unknown
+}
+
+export interface SettingsImportPreview {
+  version: number
+  exported_at: string
+  changes: SettingsFieldChange[]
 }
 
 export interface StartupState {
   enabled: boolean
+  portable: boolean
+}
+
+export interface AppSettingsSnapshot {
+  solar: SolarSettings
+  language: LanguageSettings
+  startup: StartupState
+  geocoder: GeocoderSettings
+  ip_location: IpLocationSettings
+  location_auto_update: LocationAutoUpdateSettings
+  proxy: ProxySettings
+  saved_locations: SavedLocations
+}
+
+export interface AppSettingsPatch {
+  solar?: {
+    auto_theme_enabled?: boolean
+    sunset_offset_minutes?: number
+    dry_run_enabled?: boolean
+    prefer_offline_geocoding?: boolean
+  }
+  language_preference?: string
+  startup_enabled?: boolean
+  geocoder?: {
+    provider?: GeocoderProvider
+    endpoint?: string
+  }
+  ip_location?: {
+    enabled?: boolean
+    endpoint?: string
+  }
+  location_auto_update?: {
+    enabled?: boolean
+    interval_hours?: number
+    min_distance_km?: number
+  }
+  proxy?: {
+    url?: string
+    username?: string
+    password?: string
+  }
+}
+
+export type ResetScope = 'all' | 'solar' | 'language' | 'startup'
+
+export interface SettingsChange {
+  field: string
+  previous: unknown
+  current: unknown
+}
+
+export interface SettingsChanged {
+  changes: SettingsChange[]
+  settings: AppSettingsSnapshot
 }
 
 export interface SunTimesResult {
@@ -55,6 +250,33 @@ export interface SunTimesResult {
   seconds_until_next_transition: number
 }
 
+export interface AutoThemeHistoryEntry {
+  at_local: string
+  at_unix: number
+  previous: 'light' | 'dark'
+  theme: 'light' | 'dark'
+  simulated: boolean
+  dry_run: boolean
+}
+
+export interface ThemeDryRunChange {
+  current: ThemeState
+  intended: ThemeState
+}
+
+export interface AutoThemeSimulationStatus {
+  run_id: number
+  running: boolean
+  days: number
+  speed: number
+  simulated_start: string
+  simulated_end: string
+  simulated_now: string
+  theme: 'light' | 'dark'
+  switch_count: number
+  error: AppErrorPayload | null
+}
+
 export const getThemeState = (): Promise<ThemeState> => {
   return invoke('get_theme_state')
 }
@@ -75,6 +297,21 @@ export const geocodeAddress = (address: string): Promise<GeocodeResult> => {
   return invoke('geocode_address', { address })
 }
 
+export const searchLocations = (
+  query: string,
+  limit?: number,
+  countryCodes?: string[],
+): Promise<LocationCandidate[]> => {
+  return invoke('search_locations', { query, limit, countryCodes })
+}
+
+export const reverseGeocode = (
+  latitude: number,
+  longitude: number,
+): Promise<LocationCandidate> => {
+  return invoke('reverse_geocode', { latitude, longitude })
+}
+
 export const getSolarSettings = (): Promise<SolarSettings> => {
   return invoke('get_solar_settings')
 }
@@ -83,6 +320,14 @@ export const saveSolarLocation = (address: string): Promise<SolarSettings> => {
   return invoke('save_solar_location', { address })
 }
 
+export const saveSolarCoordinates = (
+  latitude: number,
+  longitude: number,
+  label: string,
+): Promise<SolarSettings> => {
+  return invoke('save_solar_coordinates', { latitude, longitude, label })
+}
+
 export const setAutoThemeEnabled = (enabled: boolean): Promise<SolarSettings> => {
   return invoke('set_auto_theme_enabled', { enabled })
 }
@@ -91,6 +336,139 @@ export const setSunsetOffsetMinutes = (minutes: number): Promise<SolarSettings>
   return invoke('set_sunset_offset_minutes', { minutes })
 }
 
+export const setDryRunEnabled = (enabled: boolean): Promise<SolarSettings> => {
+  return invoke('set_dry_run_enabled', { enabled })
+}
+
+export const setPreferOfflineGeocoding = (enabled: boolean): Promise<SolarSettings> => {
+  return invoke('set_prefer_offline_geocoding', { enabled })
+}
+
+export const getGeocoderSettings = (): Promise<GeocoderSettings> => {
+  return invoke('get_geocoder_settings')
+}
+
+export const setGeocoderSettings = (
+  provider: GeocoderProvider,
+  endpoint: string,
+): Promise<GeocoderSettings> => {
+  return invoke('set_geocoder_settings', { provider, endpoint })
+}
+
+export const clearGeocodeCache = (): Promise<void> => {
+  return invoke('clear_geocode_cache')
+}
+
+export const getProxySettings = (): Promise<ProxySettings> => {
+  return invoke('get_proxy_settings')
+}
+
+export const setProxySettings = (
+  url: string,
+  username: string,
+  password?: string,
+): Promise<ProxySettings> => {
+  return invoke('set_proxy_settings', { url, username, password })
+}
+
+export const getIpLocationSettings = (): Promise<IpLocationSettings> => {
+  return invoke('get_ip_location_settings')
+}
+
+export const setIpLocationSettings = (
+  enabled: boolean,
+  endpoint: string,
+): Promise<IpLocationSettings> => {
+  return invoke('set_ip_location_settings', { enabled, endpoint })
+}
+
+export const detectApproximateLocation = (): Promise<SolarSettings> => {
+  return invoke('detect_approximate_location')
+}
+
+export const getLocationAutoUpdateSettings = (): Promise<LocationAutoUpdateSettings> => {
+  return invoke('get_location_auto_update_settings')
+}
+
+export const setLocationAutoUpdateSettings = (
+  enabled: boolean,
+  intervalHours: number,
+  minDistanceKm: number,
+): Promise<LocationAutoUpdateSettings> => {
+  return invoke('set_location_auto_update_settings', { enabled, intervalHours, minDistanceKm })
+}
+
+export const getTimeZoneChange = (): Promise<TimeZoneChange | null> => {
+  return invoke('get_time_zone_change')
+}
+
+export const dismissTimeZoneChange = (): Promise<SolarSettings> => {
+  return invoke('dismiss_time_zone_change')
+}
+
+export const testNetworkConnection = (): Promise<ConnectionTestResult> => {
+  return invoke('test_network_connection')
+}
+
+export const getSavedLocations = (): Promise<SavedLocations> => {
+  return invoke('get_saved_locations')
+}
+
+export const addSavedLocation = (name: string, address: string): Promise<SavedLocations> => {
+  return invoke('add_saved_location', { name, address })
+}
+
+export const renameSavedLocation = (id: string, name: string): Promise<SavedLocations> => {
+  return invoke('rename_saved_location', { id, name })
+}
+
+export const deleteSavedLocation = (id: string): Promise<SavedLocations> => {
+  return invoke('delete_saved_location', { id })
+}
+
+export const activateSavedLocation = (id: string): Promise<SavedLocations> => {
+  return invoke('activate_saved_location', { id })
+}
+
+export const setSavedLocationNetworkRules = (
+  id: string,
+  rules: NetworkRule[],
+): Promise<SavedLocations> => {
+  return invoke('set_saved_location_network_rules', { id, rules })
+}
+
+export const getNetworkLocationStatus = (): Promise<NetworkLocationStatus | null> => {
+  return invoke('get_network_location_status')
+}
+
+export const getAppSettings = (): Promise<AppSettingsSnapshot> => {
+  return invoke('get_app_settings')
+}
+
+export const updateAppSettings = (patch: AppSettingsPatch): Promise<AppSettingsSnapshot> => {
+  return invoke('update_app_settings', { patch })
+}
+
+export const resetSettings = (scope: ResetScope): Promise<AppSettingsSnapshot> => {
+  return invoke('reset_settings', { scope })
+}
+
+export const getSettingsReport = (): Promise<SettingsReport> => {
+  return invoke('get_settings_report')
+}
+
+export const exportSettings = (path: string): Promise<void> => {
+  return invoke('export_settings', { path })
+}
+
+export const previewSettingsImport = (path: string): Promise<SettingsImportPreview> => {
+  return invoke('preview_settings_import', { path })
+}
+
+export const importSettings = (path: string): Promise<SettingsImportPreview> => {
+  return invoke('import_settings', { path })
+}
+
 export const getStartupState = (): Promise<StartupState> => {
   return invoke('get_startup_state')
 }
@@ -106,6 +484,14 @@ export const getSunTimesByAddress = (
   return invoke('get_sun_times_by_address', { address, date })
 }
 
+export const getSunTimesByCoordinates = (
+  latitude: number,
+  longitude: number,
+  date?: string,
+): Promise<SunTimesResult> => {
+  return invoke('get_sun_times_by_coordinates', { latitude, longitude, date })
+}
+
 export const getSunTimesBySavedLocation = (date?: string): Promise<SunTimesResult> => {
   return invoke('get_sun_times_by_saved_location', { date })
 }
@@ -114,9 +500,37 @@ export const openExternalUrl = (url: string): Promise<void> => {
   return invoke('open_external_url', { url })
 }
 
+export const getAutoThemeHistory = (): Promise<AutoThemeHistoryEntry[]> => {
+  return invoke('get_auto_theme_history')
+}
+
+export const startAutoThemeSimulation = (
+  days: number,
+  speed: number,
+): Promise<AutoThemeSimulationStatus> => {
+  return invoke('start_auto_theme_simulation', { days, speed })
+}
+
+export const stopAutoThemeSimulation = (): Promise<void> => {
+  return invoke('stop_auto_theme_simulation')
+}
+
 export const THEME_STATE_CHANGED_EVENT = 'theme-state-changed'
 export const LANGUAGE_CHANGED_EVENT = 'language-changed'
 export const SOLAR_SETTINGS_CHANGED_EVENT = 'solar-settings-changed'
 export const STARTUP_STATE_CHANGED_EVENT = 'startup-state-changed'
 export const AUTO_THEME_CONFIGURATION_REQUIRED_EVENT =
   'auto-theme-configuration-required'
+export const AUTO_THEME_HISTORY_APPENDED_EVENT = 'auto-theme-history-appended'
+export const AUTO_THEME_SIMULATION_PROGRESS_EVENT = 'auto-theme-simulation-progress'
+export const AUTO_THEME_SIMULATION_FINISHED_EVENT = 'auto-theme-simulation-finished'
+export const THEME_DRY_RUN_CHANGE_EVENT = 'theme-dry-run-change'
+export const SAVED_LOCATIONS_CHANGED_EVENT = 'saved-locations-changed'
+export const GEOCODER_SETTINGS_CHANGED_EVENT = 'geocoder-settings-changed'
+export const PROXY_SETTINGS_CHANGED_EVENT = 'proxy-settings-changed'
+export const IP_LOCATION_SETTINGS_CHANGED_EVENT = 'ip-location-settings-changed'
+export const LOCATION_AUTO_UPDATE_SETTINGS_CHANGED_EVENT =
+  'location-auto-update-settings-changed'
+export const TIME_ZONE_CHANGED_EVENT = 'time-zone-changed'
+export const NETWORK_LOCATION_STATUS_CHANGED_EVENT = 'network-location-status-changed'
+export const SETTINGS_CHANGED_EVENT = 'settings-changed'